@@ -0,0 +1,100 @@
+//! 对比默认模型的两种加载方式：完整反序列化成 HashMap，以及校验后直接访问 rkyv 的归档类型
+//!
+//! 运行：`cargo bench --bench cold_start`
+//!
+//! 每种方式都在单独的子进程中运行，这样 RSS 的统计不会相互影响
+
+use pinyinchch_model_dag::{DAG_CHAR_BYTES, DAG_PHRASE_BYTES};
+use pinyinchch_model_hmm::{
+    HMM_EMISSION_BYTES, HMM_PY2HZ_BYTES, HMM_START_BYTES, HMM_TRANSITION_BYTES,
+};
+use pinyinchch_type::archive::ArchivedModel;
+use pinyinchch_type::dag::{DagChar, DagPhrase};
+use pinyinchch_type::hmm::{HmmData, HmmEmission, HmmPy2Hz, HmmTransition};
+use rkyv::util::AlignedVec;
+use std::hint::black_box;
+use std::process::Command;
+use std::time::Instant;
+
+const OWNED: &str = "owned";
+const ARCHIVED: &str = "archived";
+
+fn main() {
+    let mode = std::env::args()
+        .skip(1)
+        .find(|arg| arg == OWNED || arg == ARCHIVED);
+    match mode.as_deref() {
+        Some(OWNED) => run(OWNED, load_owned),
+        Some(ARCHIVED) => run(ARCHIVED, load_archived),
+        _ => {
+            let exe = std::env::current_exe().expect("Couldn't get current exe");
+            for mode in [OWNED, ARCHIVED] {
+                let status = Command::new(&exe)
+                    .arg(mode)
+                    .status()
+                    .expect("Couldn't run benchmark");
+                assert!(status.success());
+            }
+        }
+    }
+}
+
+fn run(mode: &str, load: fn()) {
+    let rss_before = rss_kib();
+    let start = Instant::now();
+    load();
+    let elapsed = start.elapsed();
+    let rss_after = rss_kib();
+    match (rss_before, rss_after) {
+        (Some(before), Some(after)) => println!(
+            "{mode:>8}: cold start {elapsed:?}, RSS +{} KiB",
+            after.saturating_sub(before)
+        ),
+        _ => println!("{mode:>8}: cold start {elapsed:?}, RSS n/a"),
+    }
+}
+
+/// 原来 `embed_data!` 的方式：复制到对齐的内存中，然后反序列化
+fn load_owned() {
+    fn from_bytes<T>(bytes: &[u8]) -> T
+    where
+        T: rkyv::Archive,
+        T::Archived: for<'a> rkyv::bytecheck::CheckBytes<
+                rkyv::api::high::HighValidator<'a, rkyv::rancor::Error>,
+            > + rkyv::Deserialize<T, rkyv::api::high::HighDeserializer<rkyv::rancor::Error>>,
+    {
+        let mut aligned = AlignedVec::<16>::new();
+        aligned.extend_from_slice(bytes);
+        rkyv::from_bytes::<T, rkyv::rancor::Error>(&aligned).unwrap()
+    }
+    black_box(from_bytes::<HmmData>(HMM_START_BYTES));
+    black_box(from_bytes::<HmmEmission>(HMM_EMISSION_BYTES));
+    black_box(from_bytes::<HmmTransition>(HMM_TRANSITION_BYTES));
+    black_box(from_bytes::<HmmPy2Hz>(HMM_PY2HZ_BYTES));
+    black_box(from_bytes::<DagChar>(DAG_CHAR_BYTES));
+    black_box(from_bytes::<DagPhrase>(DAG_PHRASE_BYTES));
+}
+
+/// 现在的方式：只校验一次，之后直接查询归档类型
+fn load_archived() {
+    black_box(ArchivedModel::<HmmData>::from_static(HMM_START_BYTES, "hmm_start").unwrap());
+    black_box(
+        ArchivedModel::<HmmEmission>::from_static(HMM_EMISSION_BYTES, "hmm_emission").unwrap(),
+    );
+    black_box(
+        ArchivedModel::<HmmTransition>::from_static(HMM_TRANSITION_BYTES, "hmm_transition")
+            .unwrap(),
+    );
+    black_box(ArchivedModel::<HmmPy2Hz>::from_static(HMM_PY2HZ_BYTES, "hmm_py2hz").unwrap());
+    black_box(ArchivedModel::<DagChar>::from_static(DAG_CHAR_BYTES, "dag_char").unwrap());
+    black_box(ArchivedModel::<DagPhrase>::from_static(DAG_PHRASE_BYTES, "dag_phrase").unwrap());
+}
+
+/// 读取当前进程的 RSS，只支持 Linux
+fn rss_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
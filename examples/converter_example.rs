@@ -0,0 +1,31 @@
+use pinyinchch::converter::Converter;
+use pinyinchch_model_dag::DefaultDag;
+use pinyinchch_model_hmm::DefaultHmm;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 使用 DAG 模型，返回 3 个候选结果，默认使用对数概率
+    let converter = Converter::dag(DefaultDag::default()).path_num(3).build();
+
+    println!("DAG结果:");
+    for candidate in converter.convert("nihao") {
+        println!("分数: {}, 文本: {}", candidate.score(), candidate.text());
+        for segment in candidate.segments() {
+            println!(
+                "\t{} 对应拼音 {:?}，局部概率 {}",
+                segment.hanzi(),
+                segment.span(),
+                segment.prob()
+            );
+        }
+    }
+
+    // 使用 HMM 模型
+    let converter = Converter::hmm(DefaultHmm::default()).path_num(3).build();
+
+    println!("\nHMM结果:");
+    for candidate in converter.convert("nihao") {
+        println!("分数: {}, 文本: {}", candidate.score(), candidate.text());
+    }
+
+    Ok(())
+}
@@ -1,29 +1,19 @@
+use crate::model::{archived_entries, archived_phrase};
 use crate::{DAG_CHAR, DAG_PHRASE};
-use pinyinchch_type::dag::Dag;
+use pinyinchch_type::dag::{Dag, DagEntries};
 
-/// 默认DAG参数实现
+/// 默认DAG参数实现，直接查询编译进二进制的 rkyv 数据，不需要反序列化
 #[derive(Default)]
 pub struct DefaultDag {}
 
 impl Dag for DefaultDag {
     fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
-        if pinyin_list.is_empty() {
-            return Vec::new();
-        }
-
-        let pinyin_key = pinyin_list.join(",");
-
-        let data = if pinyin_list.len() == 1 {
-            DAG_CHAR.data.get(&pinyin_key)
-        } else {
-            DAG_PHRASE.data.get(&pinyin_key)
-        };
+        archived_phrase(DAG_CHAR.get(), DAG_PHRASE.get(), pinyin_list, num)
+    }
+}
 
-        if let Some(phrase_data) = data {
-            let take_num = std::cmp::min(num, phrase_data.len());
-            phrase_data[..take_num].to_vec()
-        } else {
-            Vec::new()
-        }
+impl DagEntries for DefaultDag {
+    fn for_each_entry(&self, f: &mut dyn FnMut(&[&str], &str, f64)) {
+        archived_entries(DAG_CHAR.get(), DAG_PHRASE.get(), f)
     }
 }
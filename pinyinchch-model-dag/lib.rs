@@ -1,6 +1,8 @@
 mod default;
+mod model;
 
 pub use default::*;
+pub use model::*;
 
 use pinyinchch_type::dag::{DagChar, DagPhrase};
 use pinyinchch_type::embed_data;
@@ -26,4 +28,10 @@ mod tests {
         let a = dag_char.data.get("a").unwrap();
         assert_eq!(a[1].0, "\u{554a}".to_owned());
     }
+
+    #[test]
+    fn test_embedded_dag_char_is_aligned() {
+        assert_eq!(DAG_CHAR_BYTES.as_ptr().align_offset(16), 0);
+        assert_eq!(super::DAG_CHAR.get().data.len(), 433);
+    }
 }
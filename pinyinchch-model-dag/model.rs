@@ -0,0 +1,213 @@
+use pinyinchch_type::archive::{ArchivedModel, deserialize, deserialize_file};
+use pinyinchch_type::dag::{
+    ArchivedDagChar, ArchivedDagPhrase, Dag, DagChar, DagEntries, DagPhrase,
+};
+use pinyinchch_type::error::ModelError;
+use std::path::Path;
+
+const CHAR_FILE: &str = "dag_char.rkyv";
+const PHRASE_FILE: &str = "dag_phrase.rkyv";
+
+/// 运行时加载的 DAG 模型，数据会被反序列化到内存中
+#[derive(Debug)]
+pub struct DagModel {
+    char: DagChar,
+    phrase: DagPhrase,
+}
+
+impl DagModel {
+    /// 从 rkyv 格式的数据中加载模型
+    pub fn from_bytes(char: &[u8], phrase: &[u8]) -> Result<Self, ModelError> {
+        Ok(DagModel {
+            char: deserialize(char, CHAR_FILE)?,
+            phrase: deserialize(phrase, PHRASE_FILE)?,
+        })
+    }
+
+    /// 从目录中加载模型，目录中需要有 dag_char.rkyv 和 dag_phrase.rkyv
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        Ok(DagModel {
+            char: deserialize_file(path.join(CHAR_FILE))?,
+            phrase: deserialize_file(path.join(PHRASE_FILE))?,
+        })
+    }
+}
+
+impl Dag for DagModel {
+    fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+        if pinyin_list.is_empty() {
+            return Vec::new();
+        }
+
+        let pinyin_key = pinyin_list.join(",");
+
+        let data = if pinyin_list.len() == 1 {
+            self.char.data.get(&pinyin_key)
+        } else {
+            self.phrase.data.get(&pinyin_key)
+        };
+
+        if let Some(phrase_data) = data {
+            let take_num = std::cmp::min(num, phrase_data.len());
+            phrase_data[..take_num].to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl DagEntries for DagModel {
+    fn for_each_entry(&self, f: &mut dyn FnMut(&[&str], &str, f64)) {
+        for (pinyin_key, phrase_data) in self.char.data.iter().chain(&self.phrase.data) {
+            let pinyin_list = pinyin_key.split(',').collect::<Vec<_>>();
+            for (phrase, prob) in phrase_data {
+                f(&pinyin_list, phrase, *prob);
+            }
+        }
+    }
+}
+
+/// 零拷贝的 DAG 模型，直接在 rkyv 的归档类型上查询，不需要反序列化
+pub struct ArchivedDagModel {
+    char: ArchivedModel<DagChar>,
+    phrase: ArchivedModel<DagPhrase>,
+}
+
+impl ArchivedDagModel {
+    /// 从 rkyv 格式的数据中加载模型，数据只会被校验一次
+    pub fn from_bytes(char: &[u8], phrase: &[u8]) -> Result<Self, ModelError> {
+        Ok(ArchivedDagModel {
+            char: ArchivedModel::from_bytes(char, CHAR_FILE)?,
+            phrase: ArchivedModel::from_bytes(phrase, PHRASE_FILE)?,
+        })
+    }
+
+    /// 从目录中加载模型，文件要求和 [`DagModel::from_path`] 一致
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        Ok(ArchivedDagModel {
+            char: ArchivedModel::from_path(path.join(CHAR_FILE))?,
+            phrase: ArchivedModel::from_path(path.join(PHRASE_FILE))?,
+        })
+    }
+
+    /// 通过内存映射加载目录中的模型，文件要求和 [`DagModel::from_path`] 一致
+    ///
+    /// # Safety
+    ///
+    /// 模型使用期间，文件不能被修改或者截断
+    #[cfg(feature = "mmap")]
+    pub unsafe fn mmap(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        unsafe {
+            Ok(ArchivedDagModel {
+                char: ArchivedModel::mmap(path.join(CHAR_FILE))?,
+                phrase: ArchivedModel::mmap(path.join(PHRASE_FILE))?,
+            })
+        }
+    }
+}
+
+impl Dag for ArchivedDagModel {
+    fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+        archived_phrase(self.char.get(), self.phrase.get(), pinyin_list, num)
+    }
+}
+
+impl DagEntries for ArchivedDagModel {
+    fn for_each_entry(&self, f: &mut dyn FnMut(&[&str], &str, f64)) {
+        archived_entries(self.char.get(), self.phrase.get(), f)
+    }
+}
+
+pub(crate) fn archived_entries(
+    char: &ArchivedDagChar,
+    phrase: &ArchivedDagPhrase,
+    f: &mut dyn FnMut(&[&str], &str, f64),
+) {
+    for (pinyin_key, phrase_data) in char.data.iter().chain(phrase.data.iter()) {
+        let pinyin_list = pinyin_key.split(',').collect::<Vec<_>>();
+        for pair in phrase_data.iter() {
+            f(&pinyin_list, &pair.0, pair.1.to_native());
+        }
+    }
+}
+
+pub(crate) fn archived_phrase(
+    char: &ArchivedDagChar,
+    phrase: &ArchivedDagPhrase,
+    pinyin_list: &[&str],
+    num: usize,
+) -> Vec<(String, f64)> {
+    if pinyin_list.is_empty() {
+        return Vec::new();
+    }
+
+    let pinyin_key = pinyin_list.join(",");
+
+    let data = if pinyin_list.len() == 1 {
+        char.data.get(pinyin_key.as_str())
+    } else {
+        phrase.data.get(pinyin_key.as_str())
+    };
+
+    if let Some(phrase_data) = data {
+        phrase_data
+            .iter()
+            .take(num)
+            .map(|pair| (pair.0.to_string(), pair.1.to_native()))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchivedDagModel, DagModel};
+    use crate::{DAG_CHAR_BYTES, DAG_PHRASE_BYTES, DefaultDag};
+    use pinyinchch_type::dag::{Dag, DagEntries};
+    use pinyinchch_type::error::ModelError;
+
+    #[test]
+    fn test_load_dag_model_from_bytes() {
+        let model = DagModel::from_bytes(DAG_CHAR_BYTES, DAG_PHRASE_BYTES).unwrap();
+        let archived = ArchivedDagModel::from_bytes(DAG_CHAR_BYTES, DAG_PHRASE_BYTES).unwrap();
+        let default = DefaultDag::default();
+        assert_eq!(model.get_phrase(&["a"], 3), default.get_phrase(&["a"], 3));
+        assert_eq!(
+            archived.get_phrase(&["a"], 3),
+            default.get_phrase(&["a"], 3)
+        );
+    }
+
+    #[test]
+    fn test_dag_model_entries() {
+        let model = DagModel::from_bytes(DAG_CHAR_BYTES, DAG_PHRASE_BYTES).unwrap();
+        let mut owned = Vec::new();
+        model.for_each_entry(&mut |pinyin_list, phrase, prob| {
+            owned.push((pinyin_list.join(","), phrase.to_owned(), prob));
+        });
+        let mut archived = Vec::new();
+        DefaultDag::default().for_each_entry(&mut |pinyin_list, phrase, prob| {
+            archived.push((pinyin_list.join(","), phrase.to_owned(), prob));
+        });
+        owned.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        archived.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(owned, archived);
+        assert!(
+            owned
+                .iter()
+                .any(|entry| entry.0 == "a" && entry.1 == "\u{554a}")
+        );
+    }
+
+    #[test]
+    fn test_load_corrupt_dag_model() {
+        let result = ArchivedDagModel::from_bytes(&DAG_CHAR_BYTES[..64], DAG_PHRASE_BYTES);
+        assert!(matches!(result, Err(ModelError::InvalidData { .. })));
+        let result = DagModel::from_path("not_exist");
+        assert!(matches!(result, Err(ModelError::ReadFile { .. })));
+    }
+}
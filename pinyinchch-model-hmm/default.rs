@@ -1,50 +1,25 @@
+use crate::model::{archived_emission, archived_start, archived_states, archived_transition};
 use crate::{HMM_EMISSION, HMM_PY2HZ, HMM_START, HMM_TRANSITION};
 use pinyinchch_type::hmm::Hmm;
 
-const DEFAULT: &str = "default";
-
-/// 默认 HMM 实现
+/// 默认 HMM 实现，直接查询编译进二进制的 rkyv 数据，不需要反序列化
 #[derive(Default)]
 pub struct DefaultHmm {}
 
 impl Hmm for DefaultHmm {
     fn start(&self, state: &str) -> f64 {
-        if let Some(prob) = HMM_START.data.get(state) {
-            *prob
-        } else {
-            HMM_START.default
-        }
+        archived_start(HMM_START.get(), state)
     }
 
     fn emission(&self, state: &str, observation: &str) -> f64 {
-        if let Some(prob_dict) = HMM_EMISSION.data.get(state)
-            && let Some(prob) = prob_dict.get(observation)
-        {
-            *prob
-        } else {
-            HMM_EMISSION.default
-        }
+        archived_emission(HMM_EMISSION.get(), state, observation)
     }
 
     fn transition(&self, from_state: &str, to_state: &str) -> f64 {
-        if let Some(prob_dict) = HMM_TRANSITION.data.get(from_state) {
-            if let Some(prob) = prob_dict.get(to_state) {
-                *prob
-            } else if let Some(default_prob) = prob_dict.get(DEFAULT) {
-                *default_prob
-            } else {
-                HMM_TRANSITION.default
-            }
-        } else {
-            HMM_TRANSITION.default
-        }
+        archived_transition(HMM_TRANSITION.get(), from_state, to_state)
     }
 
     fn get_states(&self, observation: &str) -> Vec<String> {
-        if let Some(hanzi_string) = HMM_PY2HZ.data.get(observation) {
-            hanzi_string.chars().map(|c| c.to_string()).collect()
-        } else {
-            Vec::new()
-        }
+        archived_states(HMM_PY2HZ.get(), observation)
     }
 }
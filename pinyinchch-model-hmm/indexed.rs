@@ -0,0 +1,161 @@
+use crate::model::{DEFAULT, HmmModel};
+use pinyinchch_type::hmm::{CsrMatrix, IndexedHmm, ObsId, StateId};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// 使用整数编号的 HMM 模型，起始概率是稠密的数组，发射概率和转移概率保存在 [`CsrMatrix`] 中
+///
+/// 由 [`HmmModel`] 转换得到，如 `IndexedHmmModel::from(HmmModel::from_path(path)?)`
+#[derive(Debug)]
+pub struct IndexedHmmModel {
+    states: Vec<String>,
+    state_ids: HashMap<String, StateId>,
+    observations: HashMap<String, ObsId>,
+    /// 每个拼音对应的汉字，顺序和 [`HmmModel::get_states`](pinyinchch_type::hmm::Hmm::get_states) 一致
+    obs_states: Vec<Vec<StateId>>,
+    start: Vec<f64>,
+    /// 行是拼音，列是汉字
+    emission: CsrMatrix,
+    emission_default: f64,
+    transition: CsrMatrix,
+    /// 每个汉字转移到没有记录的汉字时使用的概率
+    transition_defaults: Vec<f64>,
+    /// 从模型中没有的汉字转移时使用的概率
+    transition_default: f64,
+}
+
+impl IndexedHmmModel {
+    /// 模型中汉字的数量
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// 模型中拼音的数量
+    pub fn observation_count(&self) -> usize {
+        self.obs_states.len()
+    }
+}
+
+impl From<HmmModel> for IndexedHmmModel {
+    fn from(model: HmmModel) -> Self {
+        let mut states = Vec::new();
+        let mut state_ids = HashMap::new();
+        let mut intern = |state: &str| {
+            *state_ids.entry(state.to_owned()).or_insert_with(|| {
+                states.push(state.to_owned());
+                StateId(states.len() as u32 - 1)
+            })
+        };
+
+        // 按照拼音排序之后编号，保证每次转换的结果一致
+        let mut py2hz = model.py2hz.data.iter().collect::<Vec<_>>();
+        py2hz.sort_unstable();
+        let mut observations = HashMap::with_capacity(py2hz.len());
+        let mut obs_states = Vec::with_capacity(py2hz.len());
+        for (obs, hanzi) in py2hz {
+            observations.insert(obs.clone(), ObsId(obs_states.len() as u32));
+            obs_states.push(
+                hanzi
+                    .chars()
+                    .map(|c| intern(c.encode_utf8(&mut [0; 4])))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        // 不在拼音表中的汉字也可能作为上下文使用
+        let mut extra = model
+            .start
+            .data
+            .keys()
+            .chain(model.transition.data.keys())
+            .collect::<Vec<_>>();
+        extra.sort_unstable();
+        for state in extra {
+            intern(state);
+        }
+
+        let mut start = vec![model.start.default; states.len()];
+        for (state, prob) in &model.start.data {
+            start[state_ids[state].0 as usize] = *prob;
+        }
+
+        let mut emission_rows = vec![Vec::new(); obs_states.len()];
+        for (state, probs) in &model.emission.data {
+            let Some(state) = state_ids.get(state) else {
+                continue;
+            };
+            for (obs, prob) in probs {
+                if let Some(obs) = observations.get(obs) {
+                    emission_rows[obs.0 as usize].push((state.0, *prob));
+                }
+            }
+        }
+
+        let mut transition_rows = vec![Vec::new(); states.len()];
+        let mut transition_defaults = vec![model.transition.default; states.len()];
+        for (from_state, probs) in &model.transition.data {
+            let from_state = state_ids[from_state].0 as usize;
+            for (to_state, prob) in probs {
+                if to_state == DEFAULT {
+                    transition_defaults[from_state] = *prob;
+                } else if let Some(to_state) = state_ids.get(to_state) {
+                    transition_rows[from_state].push((to_state.0, *prob));
+                }
+            }
+        }
+
+        Self {
+            states,
+            state_ids,
+            observations,
+            obs_states,
+            start,
+            emission: CsrMatrix::from_rows(emission_rows),
+            emission_default: model.emission.default,
+            transition: CsrMatrix::from_rows(transition_rows),
+            transition_defaults,
+            transition_default: model.transition.default,
+        }
+    }
+}
+
+impl IndexedHmm for IndexedHmmModel {
+    fn observation_id(&self, observation: &str) -> Option<ObsId> {
+        self.observations.get(observation).copied()
+    }
+
+    fn state_id(&self, state: &str) -> Option<StateId> {
+        self.state_ids.get(state).copied()
+    }
+
+    fn state_name(&self, state: StateId) -> Cow<'_, str> {
+        Cow::Borrowed(&self.states[state.0 as usize])
+    }
+
+    fn states(&self, observation: ObsId) -> Cow<'_, [StateId]> {
+        Cow::Borrowed(&self.obs_states[observation.0 as usize])
+    }
+
+    fn start(&self, state: StateId) -> f64 {
+        self.start[state.0 as usize]
+    }
+
+    fn emission(&self, state: StateId, observation: ObsId) -> f64 {
+        self.emission
+            .get(observation.0, state.0)
+            .unwrap_or(self.emission_default)
+    }
+
+    fn transition(&self, from_state: StateId, to_state: StateId) -> f64 {
+        self.transition
+            .get(from_state.0, to_state.0)
+            .unwrap_or(self.transition_defaults[from_state.0 as usize])
+    }
+
+    /// 模型中没有 from_state 时和 [`HmmModel`] 一样使用默认的转移概率
+    fn transition_from_name(&self, from_state: &str, to_state: StateId) -> f64 {
+        match self.state_id(from_state) {
+            Some(from_state) => self.transition(from_state, to_state),
+            None => self.transition_default,
+        }
+    }
+}
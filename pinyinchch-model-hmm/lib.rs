@@ -1,6 +1,10 @@
 mod default;
+mod indexed;
+mod model;
 
 pub use default::*;
+pub use indexed::*;
+pub use model::*;
 
 use pinyinchch_type::embed_data;
 use pinyinchch_type::hmm::{HmmData, HmmEmission, HmmPy2Hz, HmmTransition};
@@ -0,0 +1,308 @@
+use pinyinchch_type::archive::{ArchivedModel, deserialize, deserialize_file};
+use pinyinchch_type::error::ModelError;
+use pinyinchch_type::hmm::{
+    ArchivedHmmData, ArchivedHmmEmission, ArchivedHmmPy2Hz, ArchivedHmmTransition, Hmm, HmmData,
+    HmmEmission, HmmPy2Hz, HmmTransition,
+};
+use std::path::Path;
+
+pub(crate) const DEFAULT: &str = "default";
+
+const START_FILE: &str = "hmm_start.rkyv";
+const EMISSION_FILE: &str = "hmm_emission.rkyv";
+const TRANSITION_FILE: &str = "hmm_transition.rkyv";
+const PY2HZ_FILE: &str = "hmm_py2hz.rkyv";
+
+/// 运行时加载的 HMM 模型，数据会被反序列化到内存中
+#[derive(Debug)]
+pub struct HmmModel {
+    pub(crate) start: HmmData,
+    pub(crate) emission: HmmEmission,
+    pub(crate) transition: HmmTransition,
+    pub(crate) py2hz: HmmPy2Hz,
+}
+
+impl HmmModel {
+    /// 从 rkyv 格式的数据中加载模型
+    pub fn from_bytes(
+        start: &[u8],
+        emission: &[u8],
+        transition: &[u8],
+        py2hz: &[u8],
+    ) -> Result<Self, ModelError> {
+        Ok(HmmModel {
+            start: deserialize(start, START_FILE)?,
+            emission: deserialize(emission, EMISSION_FILE)?,
+            transition: deserialize(transition, TRANSITION_FILE)?,
+            py2hz: deserialize(py2hz, PY2HZ_FILE)?,
+        })
+    }
+
+    /// 从目录中加载模型，目录中需要有 hmm_start.rkyv、hmm_emission.rkyv、hmm_transition.rkyv 和 hmm_py2hz.rkyv
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        Ok(HmmModel {
+            start: deserialize_file(path.join(START_FILE))?,
+            emission: deserialize_file(path.join(EMISSION_FILE))?,
+            transition: deserialize_file(path.join(TRANSITION_FILE))?,
+            py2hz: deserialize_file(path.join(PY2HZ_FILE))?,
+        })
+    }
+}
+
+impl Hmm for HmmModel {
+    fn start(&self, state: &str) -> f64 {
+        if let Some(prob) = self.start.data.get(state) {
+            *prob
+        } else {
+            self.start.default
+        }
+    }
+
+    fn emission(&self, state: &str, observation: &str) -> f64 {
+        if let Some(prob_dict) = self.emission.data.get(state)
+            && let Some(prob) = prob_dict.get(observation)
+        {
+            *prob
+        } else {
+            self.emission.default
+        }
+    }
+
+    fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+        if let Some(prob_dict) = self.transition.data.get(from_state) {
+            if let Some(prob) = prob_dict.get(to_state) {
+                *prob
+            } else if let Some(default_prob) = prob_dict.get(DEFAULT) {
+                *default_prob
+            } else {
+                self.transition.default
+            }
+        } else {
+            self.transition.default
+        }
+    }
+
+    fn get_states(&self, observation: &str) -> Vec<String> {
+        if let Some(hanzi_string) = self.py2hz.data.get(observation) {
+            hanzi_string.chars().map(|c| c.to_string()).collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// 零拷贝的 HMM 模型，直接在 rkyv 的归档类型上查询，不需要反序列化
+pub struct ArchivedHmmModel {
+    start: ArchivedModel<HmmData>,
+    emission: ArchivedModel<HmmEmission>,
+    transition: ArchivedModel<HmmTransition>,
+    py2hz: ArchivedModel<HmmPy2Hz>,
+}
+
+impl ArchivedHmmModel {
+    /// 从 rkyv 格式的数据中加载模型，数据只会被校验一次
+    pub fn from_bytes(
+        start: &[u8],
+        emission: &[u8],
+        transition: &[u8],
+        py2hz: &[u8],
+    ) -> Result<Self, ModelError> {
+        Ok(ArchivedHmmModel {
+            start: ArchivedModel::from_bytes(start, START_FILE)?,
+            emission: ArchivedModel::from_bytes(emission, EMISSION_FILE)?,
+            transition: ArchivedModel::from_bytes(transition, TRANSITION_FILE)?,
+            py2hz: ArchivedModel::from_bytes(py2hz, PY2HZ_FILE)?,
+        })
+    }
+
+    /// 从目录中加载模型，文件要求和 [`HmmModel::from_path`] 一致
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        Ok(ArchivedHmmModel {
+            start: ArchivedModel::from_path(path.join(START_FILE))?,
+            emission: ArchivedModel::from_path(path.join(EMISSION_FILE))?,
+            transition: ArchivedModel::from_path(path.join(TRANSITION_FILE))?,
+            py2hz: ArchivedModel::from_path(path.join(PY2HZ_FILE))?,
+        })
+    }
+
+    /// 通过内存映射加载目录中的模型，文件要求和 [`HmmModel::from_path`] 一致
+    ///
+    /// # Safety
+    ///
+    /// 模型使用期间，文件不能被修改或者截断
+    #[cfg(feature = "mmap")]
+    pub unsafe fn mmap(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        unsafe {
+            Ok(ArchivedHmmModel {
+                start: ArchivedModel::mmap(path.join(START_FILE))?,
+                emission: ArchivedModel::mmap(path.join(EMISSION_FILE))?,
+                transition: ArchivedModel::mmap(path.join(TRANSITION_FILE))?,
+                py2hz: ArchivedModel::mmap(path.join(PY2HZ_FILE))?,
+            })
+        }
+    }
+}
+
+impl Hmm for ArchivedHmmModel {
+    fn start(&self, state: &str) -> f64 {
+        archived_start(self.start.get(), state)
+    }
+
+    fn emission(&self, state: &str, observation: &str) -> f64 {
+        archived_emission(self.emission.get(), state, observation)
+    }
+
+    fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+        archived_transition(self.transition.get(), from_state, to_state)
+    }
+
+    fn get_states(&self, observation: &str) -> Vec<String> {
+        archived_states(self.py2hz.get(), observation)
+    }
+}
+
+pub(crate) fn archived_start(start: &ArchivedHmmData, state: &str) -> f64 {
+    if let Some(prob) = start.data.get(state) {
+        prob.to_native()
+    } else {
+        start.default.to_native()
+    }
+}
+
+pub(crate) fn archived_emission(
+    emission: &ArchivedHmmEmission,
+    state: &str,
+    observation: &str,
+) -> f64 {
+    if let Some(prob_dict) = emission.data.get(state)
+        && let Some(prob) = prob_dict.get(observation)
+    {
+        prob.to_native()
+    } else {
+        emission.default.to_native()
+    }
+}
+
+pub(crate) fn archived_transition(
+    transition: &ArchivedHmmTransition,
+    from_state: &str,
+    to_state: &str,
+) -> f64 {
+    if let Some(prob_dict) = transition.data.get(from_state) {
+        if let Some(prob) = prob_dict.get(to_state) {
+            prob.to_native()
+        } else if let Some(default_prob) = prob_dict.get(DEFAULT) {
+            default_prob.to_native()
+        } else {
+            transition.default.to_native()
+        }
+    } else {
+        transition.default.to_native()
+    }
+}
+
+pub(crate) fn archived_states(py2hz: &ArchivedHmmPy2Hz, observation: &str) -> Vec<String> {
+    if let Some(hanzi_string) = py2hz.data.get(observation) {
+        hanzi_string.chars().map(|c| c.to_string()).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchivedHmmModel, HmmModel};
+    use crate::{
+        DefaultHmm, HMM_EMISSION_BYTES, HMM_PY2HZ_BYTES, HMM_START_BYTES, HMM_TRANSITION_BYTES,
+        IndexedHmmModel,
+    };
+    use pinyinchch_type::error::ModelError;
+    use pinyinchch_type::hmm::{Hmm, IndexedHmm};
+
+    #[test]
+    fn test_load_hmm_model_from_bytes() {
+        let model = HmmModel::from_bytes(
+            HMM_START_BYTES,
+            HMM_EMISSION_BYTES,
+            HMM_TRANSITION_BYTES,
+            HMM_PY2HZ_BYTES,
+        )
+        .unwrap();
+        let archived = ArchivedHmmModel::from_bytes(
+            HMM_START_BYTES,
+            HMM_EMISSION_BYTES,
+            HMM_TRANSITION_BYTES,
+            HMM_PY2HZ_BYTES,
+        )
+        .unwrap();
+        let default = DefaultHmm::default();
+        assert_eq!(model.start("一"), default.start("一"));
+        assert_eq!(archived.start("一"), default.start("一"));
+        assert_eq!(
+            archived.emission("丁", "ding"),
+            default.emission("丁", "ding")
+        );
+        assert_eq!(archived.get_states("a"), default.get_states("a"));
+    }
+
+    #[test]
+    fn test_indexed_hmm_model() {
+        let model = HmmModel::from_bytes(
+            HMM_START_BYTES,
+            HMM_EMISSION_BYTES,
+            HMM_TRANSITION_BYTES,
+            HMM_PY2HZ_BYTES,
+        )
+        .unwrap();
+        let default = DefaultHmm::default();
+        let indexed = IndexedHmmModel::from(model);
+        assert!(indexed.observation_id("not_exist").is_none());
+
+        // 和字符串版本的模型查询到相同的汉字和概率
+        for obs in ["a", "ding", "zhong", "guo"] {
+            let obs_id = indexed.observation_id(obs).unwrap();
+            let states = indexed.states(obs_id);
+            let names = states
+                .iter()
+                .map(|state| indexed.state_name(*state).into_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(names, default.get_states(obs));
+            for (state, name) in states.iter().zip(&names) {
+                assert_eq!(indexed.state_id(name), Some(*state));
+                assert_eq!(indexed.start(*state), default.start(name));
+                assert_eq!(
+                    indexed.emission(*state, obs_id),
+                    default.emission(name, obs)
+                );
+                for (to_state, to_name) in states.iter().zip(&names) {
+                    assert_eq!(
+                        indexed.transition(*state, *to_state),
+                        default.transition(name, to_name)
+                    );
+                }
+                // 模型中没有的上下文使用默认的转移概率
+                assert!(indexed.state_id("xyz").is_none());
+                assert_eq!(
+                    indexed.transition_from_name("xyz", *state),
+                    default.transition("xyz", name)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_corrupt_hmm_model() {
+        let result = HmmModel::from_bytes(
+            &[1, 2, 3],
+            HMM_EMISSION_BYTES,
+            HMM_TRANSITION_BYTES,
+            HMM_PY2HZ_BYTES,
+        );
+        assert!(matches!(result, Err(ModelError::InvalidData { .. })));
+        let result = ArchivedHmmModel::from_path("not_exist");
+        assert!(matches!(result, Err(ModelError::ReadFile { .. })));
+    }
+}
@@ -0,0 +1,155 @@
+use pinyinchch_type::archive::{ArchivedModel, deserialize, deserialize_file};
+use pinyinchch_type::error::ModelError;
+use pinyinchch_type::ngram::{LanguageModel, NgramTable, backoff_log_prob};
+use std::path::Path;
+
+const NGRAM_FILE: &str = "ngram.rkyv";
+
+/// 运行时加载的 n 元语法模型，数据会被反序列化到内存中
+#[derive(Debug)]
+pub struct NgramModel {
+    table: NgramTable,
+}
+
+impl NgramModel {
+    /// 使用已经训练好的数据
+    pub fn new(table: NgramTable) -> Self {
+        NgramModel { table }
+    }
+
+    /// 从 rkyv 格式的数据中加载模型
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ModelError> {
+        Ok(NgramModel {
+            table: deserialize(bytes, NGRAM_FILE)?,
+        })
+    }
+
+    /// 从目录中加载模型，目录中需要有 ngram.rkyv
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        Ok(NgramModel {
+            table: deserialize_file(path.as_ref().join(NGRAM_FILE))?,
+        })
+    }
+}
+
+impl LanguageModel for NgramModel {
+    fn order(&self) -> usize {
+        self.table.order as usize
+    }
+
+    fn log_prob(&self, context: &[&str], word: &str) -> f64 {
+        backoff_log_prob(self.order(), context, word, self.table.unknown, |key| {
+            self.table.data.get(key).copied()
+        })
+    }
+}
+
+/// 零拷贝的 n 元语法模型，直接在 rkyv 的归档类型上查询，不需要反序列化
+pub struct ArchivedNgramModel {
+    table: ArchivedModel<NgramTable>,
+}
+
+impl ArchivedNgramModel {
+    /// 从 rkyv 格式的数据中加载模型，数据只会被校验一次
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ModelError> {
+        Ok(ArchivedNgramModel {
+            table: ArchivedModel::from_bytes(bytes, NGRAM_FILE)?,
+        })
+    }
+
+    /// 从目录中加载模型，文件要求和 [`NgramModel::from_path`] 一致
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        Ok(ArchivedNgramModel {
+            table: ArchivedModel::from_path(path.as_ref().join(NGRAM_FILE))?,
+        })
+    }
+
+    /// 通过内存映射加载目录中的模型，文件要求和 [`NgramModel::from_path`] 一致
+    ///
+    /// # Safety
+    ///
+    /// 模型使用期间，文件不能被修改或者截断
+    #[cfg(feature = "mmap")]
+    pub unsafe fn mmap(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        unsafe {
+            Ok(ArchivedNgramModel {
+                table: ArchivedModel::mmap(path.as_ref().join(NGRAM_FILE))?,
+            })
+        }
+    }
+}
+
+impl LanguageModel for ArchivedNgramModel {
+    fn order(&self) -> usize {
+        self.table.get().order.to_native() as usize
+    }
+
+    fn log_prob(&self, context: &[&str], word: &str) -> f64 {
+        let table = self.table.get();
+        backoff_log_prob(
+            self.order(),
+            context,
+            word,
+            table.unknown.to_native(),
+            |key| {
+                table
+                    .data
+                    .get(key)
+                    .map(|pair| (pair.0.to_native(), pair.1.to_native()))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchivedNgramModel, NgramModel};
+    use pinyinchch_type::error::ModelError;
+    use pinyinchch_type::ngram::{LanguageModel, NgramTable};
+
+    fn table() -> NgramTable {
+        let data = [
+            ("<s>", (-99.0, -0.5)),
+            ("你", (-1.0, -0.2)),
+            ("好", (-1.5, -0.3)),
+            ("<s> 你", (-0.4, 0.0)),
+            ("你 好", (-0.1, 0.0)),
+        ];
+        NgramTable {
+            order: 2,
+            data: data
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+            unknown: -10.0,
+        }
+    }
+
+    #[test]
+    fn test_ngram_model() {
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&table()).unwrap();
+        let model = NgramModel::from_bytes(&bytes).unwrap();
+        let archived = ArchivedNgramModel::from_bytes(&bytes).unwrap();
+        assert_eq!(model.order(), 2);
+        assert_eq!(archived.order(), 2);
+        for (context, word, log_prob) in [
+            (&["你"][..], "好", -0.1),
+            // 没有 "好 你"，使用 "好" 的回退权重和 "你" 的概率
+            (&["<s>", "好"][..], "你", -0.3 + -1.0),
+            (&[][..], "好", -1.5),
+            (&["你"][..], "未知", -0.2 + -10.0),
+        ] {
+            assert_eq!(model.log_prob(context, word), log_prob);
+            assert_eq!(archived.log_prob(context, word), log_prob);
+        }
+    }
+
+    #[test]
+    fn test_load_corrupt_ngram_model() {
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&table()).unwrap();
+        let result = ArchivedNgramModel::from_bytes(&bytes[..8]);
+        assert!(matches!(result, Err(ModelError::InvalidData { .. })));
+        let result = NgramModel::from_path("not_exist");
+        assert!(matches!(result, Err(ModelError::ReadFile { .. })));
+    }
+}
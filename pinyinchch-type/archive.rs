@@ -0,0 +1,142 @@
+use crate::error::{InvalidDataSnafu, ModelError, ReadFileSnafu};
+use rkyv::api::high::{HighDeserializer, HighValidator};
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor::Error;
+use rkyv::util::AlignedVec;
+use rkyv::{Archive, Deserialize, Portable};
+use snafu::ResultExt;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::path::Path;
+
+/// 按 16 字节对齐的数据，用来保证 `include_bytes!` 引入的数据可以直接被 rkyv 访问
+#[repr(C, align(16))]
+pub struct Aligned<B: ?Sized>(pub B);
+
+/// 保存 rkyv 数据的字节，起始地址满足 rkyv 的对齐要求
+pub enum ModelBytes {
+    /// 编译进二进制的数据
+    Static(&'static [u8]),
+    /// 从文件或者内存中复制的数据
+    Owned(AlignedVec<16>),
+    /// 通过内存映射读取的文件
+    #[cfg(feature = "mmap")]
+    Mmap(memmap2::Mmap),
+}
+
+impl Deref for ModelBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ModelBytes::Static(bytes) => bytes,
+            ModelBytes::Owned(bytes) => bytes.as_slice(),
+            #[cfg(feature = "mmap")]
+            ModelBytes::Mmap(mmap) => mmap,
+        }
+    }
+}
+
+/// 经过校验的 rkyv 数据，可以不经过反序列化，直接访问归档类型
+pub struct ArchivedModel<T> {
+    bytes: ModelBytes,
+    _marker: PhantomData<T>,
+}
+
+impl<T> ArchivedModel<T>
+where
+    T: Archive,
+    T::Archived: Portable + for<'a> CheckBytes<HighValidator<'a, Error>>,
+{
+    /// 校验数据，数据损坏时返回错误
+    pub fn new(bytes: ModelBytes, name: &str) -> Result<Self, ModelError> {
+        rkyv::access::<T::Archived, Error>(&bytes).context(InvalidDataSnafu { name })?;
+        Ok(ArchivedModel {
+            bytes,
+            _marker: PhantomData,
+        })
+    }
+
+    /// 复制一份对齐的数据
+    pub fn from_bytes(bytes: &[u8], name: &str) -> Result<Self, ModelError> {
+        let mut aligned = AlignedVec::<16>::new();
+        aligned.extend_from_slice(bytes);
+        Self::new(ModelBytes::Owned(aligned), name)
+    }
+
+    /// 直接使用静态数据，只有地址没有对齐时才会复制
+    pub fn from_static(bytes: &'static [u8], name: &str) -> Result<Self, ModelError> {
+        if bytes.as_ptr().align_offset(align_of::<Aligned<[u8; 0]>>()) == 0 {
+            Self::new(ModelBytes::Static(bytes), name)
+        } else {
+            Self::from_bytes(bytes, name)
+        }
+    }
+
+    /// 将文件读入内存
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        Self::new(ModelBytes::Owned(read_aligned(path)?), &file_name(path))
+    }
+
+    /// 将文件映射到内存中，只有访问到的页会被读取
+    ///
+    /// # Safety
+    ///
+    /// 映射期间文件不能被修改或者截断，否则会出现未定义行为
+    #[cfg(feature = "mmap")]
+    pub unsafe fn mmap(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        let file = File::open(path).context(ReadFileSnafu { path })?;
+        let mmap = unsafe { memmap2::Mmap::map(&file) }.context(ReadFileSnafu { path })?;
+        Self::new(ModelBytes::Mmap(mmap), &file_name(path))
+    }
+
+    /// 获取归档类型
+    pub fn get(&self) -> &T::Archived {
+        // SAFETY: 创建时已经校验过数据
+        unsafe { rkyv::access_unchecked::<T::Archived>(&self.bytes) }
+    }
+}
+
+/// 将 rkyv 数据反序列化成拥有所有权的类型
+pub fn deserialize<T>(bytes: &[u8], name: &str) -> Result<T, ModelError>
+where
+    T: Archive,
+    T::Archived:
+        for<'a> CheckBytes<HighValidator<'a, Error>> + Deserialize<T, HighDeserializer<Error>>,
+{
+    let mut aligned = AlignedVec::<16>::new();
+    aligned.extend_from_slice(bytes);
+    rkyv::from_bytes::<T, Error>(&aligned).context(InvalidDataSnafu { name })
+}
+
+/// 读取 rkyv 文件并反序列化
+pub fn deserialize_file<T>(path: impl AsRef<Path>) -> Result<T, ModelError>
+where
+    T: Archive,
+    T::Archived:
+        for<'a> CheckBytes<HighValidator<'a, Error>> + Deserialize<T, HighDeserializer<Error>>,
+{
+    let path = path.as_ref();
+    let aligned = read_aligned(path)?;
+    rkyv::from_bytes::<T, Error>(&aligned).context(InvalidDataSnafu {
+        name: file_name(path),
+    })
+}
+
+fn read_aligned(path: &Path) -> Result<AlignedVec<16>, ModelError> {
+    let mut file = File::open(path).context(ReadFileSnafu { path })?;
+    let mut aligned = AlignedVec::<16>::new();
+    aligned
+        .extend_from_reader(&mut file)
+        .context(ReadFileSnafu { path })?;
+    Ok(aligned)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
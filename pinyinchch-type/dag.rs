@@ -6,6 +6,12 @@ pub trait Dag {
     fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)>;
 }
 
+/// 遍历 DAG 模型中的所有词条，用于构建汉字到拼音的反向索引
+pub trait DagEntries {
+    /// 依次访问每个词条，参数是 (拼音列表, 词组, 概率)，单字的拼音列表只有一个拼音
+    fn for_each_entry(&self, f: &mut dyn FnMut(&[&str], &str, f64));
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[cfg_attr(
@@ -0,0 +1,18 @@
+use snafu::Snafu;
+use std::path::PathBuf;
+
+/// 加载模型文件时可能出现的错误
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum ModelError {
+    #[snafu(display("Couldn't read model file {}", path.display()))]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("The model data {name} is corrupt"))]
+    InvalidData {
+        name: String,
+        source: rkyv::rancor::Error,
+    },
+}
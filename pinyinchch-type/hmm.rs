@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 /// HMM 模型所需的方法
@@ -15,6 +16,117 @@ pub trait Hmm {
     fn get_states(&self, observation: &str) -> Vec<String>;
 }
 
+/// 状态（汉字）在 [`IndexedHmm`] 中的编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateId(pub u32);
+
+/// 观测值（拼音）在 [`IndexedHmm`] 中的编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObsId(pub u32);
+
+/// 使用整数编号的 HMM 模型所需的方法
+///
+/// 解码前先把拼音和汉字转换成编号，Viterbi 算法的内层循环中只需要按编号查询概率，不需要分配和比较字符串
+pub trait IndexedHmm {
+    /// 获取观测值（拼音）的编号，模型中没有这个拼音时返回 None
+    fn observation_id(&self, observation: &str) -> Option<ObsId>;
+
+    /// 获取状态（汉字）的编号，模型中没有这个汉字时返回 None
+    fn state_id(&self, state: &str) -> Option<StateId>;
+
+    /// 获取编号对应的状态（汉字）
+    fn state_name(&self, state: StateId) -> Cow<'_, str>;
+
+    /// 获取能够产生给定观测值（拼音）的所有状态（汉字）
+    fn states(&self, observation: ObsId) -> Cow<'_, [StateId]>;
+
+    /// 获取状态（汉字）的起始概率
+    fn start(&self, state: StateId) -> f64;
+
+    /// 获取状态（汉字）到观测值（拼音）的发射概率
+    fn emission(&self, state: StateId, observation: ObsId) -> f64;
+
+    /// 获取状态转移概率（从一个汉字到另一个汉字）
+    fn transition(&self, from_state: StateId, to_state: StateId) -> f64;
+
+    /// 获取从汉字 from_state 转移到 to_state 的概率，用于没有编号的上下文，模型中没有 from_state 时返回 0
+    fn transition_from_name(&self, from_state: &str, to_state: StateId) -> f64 {
+        self.state_id(from_state)
+            .map_or(0.0, |from_state| self.transition(from_state, to_state))
+    }
+}
+
+/// 按行压缩（CSR）的稀疏矩阵，每一行的列号升序排列，查询时二分查找
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrMatrix {
+    /// 第 i 行的数据在 columns 和 values 中的范围是 offsets[i]..offsets[i + 1]
+    offsets: Vec<usize>,
+    columns: Vec<u32>,
+    values: Vec<f64>,
+}
+
+impl Default for CsrMatrix {
+    fn default() -> Self {
+        Self {
+            offsets: vec![0],
+            columns: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl CsrMatrix {
+    /// 从每一行的 (列号, 值) 构造矩阵，同一行中重复的列号只保留最后一个
+    pub fn from_rows(rows: impl IntoIterator<Item = Vec<(u32, f64)>>) -> Self {
+        let mut matrix = Self::default();
+        for mut row in rows {
+            // 稳定排序，重复的列号保持原来的顺序
+            row.sort_by_key(|(column, _)| *column);
+            let start = matrix.columns.len();
+            for (column, value) in row {
+                if matrix.columns.len() > start && matrix.columns.last() == Some(&column) {
+                    *matrix.values.last_mut().unwrap() = value;
+                } else {
+                    matrix.columns.push(column);
+                    matrix.values.push(value);
+                }
+            }
+            matrix.offsets.push(matrix.columns.len());
+        }
+        matrix
+    }
+
+    /// 矩阵的行数
+    pub fn rows(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// 非零元素的数量
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// 查询一个元素，行号超出范围或者元素不存在时返回 None
+    pub fn get(&self, row: u32, column: u32) -> Option<f64> {
+        let (columns, values) = self.row(row);
+        columns.binary_search(&column).ok().map(|idx| values[idx])
+    }
+
+    /// 一行中的列号和值，行号超出范围时为空
+    pub fn row(&self, row: u32) -> (&[u32], &[f64]) {
+        let row = row as usize;
+        if row >= self.rows() {
+            return (&[], &[]);
+        }
+        let range = self.offsets[row]..self.offsets[row + 1];
+        (&self.columns[range.clone()], &self.values[range])
+    }
+}
+
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[cfg_attr(
@@ -1,19 +1,24 @@
+#[cfg(feature = "rkyv")]
+pub mod archive;
 pub mod dag;
+#[cfg(feature = "rkyv")]
+pub mod error;
 pub mod hmm;
+pub mod ngram;
 
+/// 将 rkyv 文件编译进二进制，第一次访问时校验数据，之后直接查询归档类型
 #[macro_export]
 macro_rules! embed_data {
     ($name:ident,$t:ty,$byte:ident,$path:literal) => {
-        pub const $byte: &'static [u8] = include_bytes!($path);
-        pub static $name: ::std::sync::LazyLock<$t> = ::std::sync::LazyLock::new(|| {
-            let mut aligned = rkyv::util::AlignedVec::<16>::new();
-            aligned.extend_from_slice($byte);
-            rkyv::from_bytes::<$t, rkyv::rancor::Error>(&aligned).expect(concat!(
-                "Failed to crate ",
-                stringify!($name),
-                "stringify!($name)",
-                $path
-            ))
-        });
+        pub static $byte: &'static [u8] = &$crate::archive::Aligned(*include_bytes!($path)).0;
+        pub static $name: ::std::sync::LazyLock<$crate::archive::ArchivedModel<$t>> =
+            ::std::sync::LazyLock::new(|| {
+                $crate::archive::ArchivedModel::from_static($byte, $path).expect(concat!(
+                    "Failed to validate ",
+                    stringify!($name),
+                    " from ",
+                    $path
+                ))
+            });
     };
 }
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+/// 句子开始的标记，没有上文时作为第一个词的上文
+pub const SENTENCE_START: &str = "<s>";
+
+/// 句子结束的标记
+pub const SENTENCE_END: &str = "</s>";
+
+/// 词语级别的语言模型所需的方法
+pub trait LanguageModel {
+    /// 模型的阶数，如二元模型是 2，查询时最多使用 order - 1 个上文
+    fn order(&self) -> usize;
+
+    /// 在上文 context 之后出现 word 的对数概率，context 按照顺序排列，最后一个是离 word 最近的词
+    fn log_prob(&self, context: &[&str], word: &str) -> f64;
+}
+
+/// 回退形式的 n 元语法模型，和 ARPA 格式一致
+///
+/// 每个 n 元组记录 (对数概率, 回退权重的对数)，n 元组中的词用空格连接。
+/// 查询的 n 元组不存在时，使用上文的回退权重乘以更短的上文下的概率
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)
+)]
+pub struct NgramTable {
+    pub order: u32,
+    pub data: HashMap<String, (f64, f64)>,
+    /// 未登录词的对数概率
+    pub unknown: f64,
+}
+
+/// 按照回退规则计算对数概率，lookup 查询一个 n 元组的 (对数概率, 回退权重的对数)
+///
+/// 先使用最长的上文，n 元组不存在时加上上文的回退权重，去掉最远的一个上文之后再查询，
+/// 单个词也不存在时使用 unknown
+pub fn backoff_log_prob(
+    order: usize,
+    context: &[&str],
+    word: &str,
+    unknown: f64,
+    lookup: impl Fn(&str) -> Option<(f64, f64)>,
+) -> f64 {
+    let context = &context[context.len().saturating_sub(order.saturating_sub(1))..];
+    let mut backoff = 0.0;
+    for start in 0..=context.len() {
+        let history = &context[start..];
+        let mut key = history.join(" ");
+        if !key.is_empty() {
+            key.push(' ');
+        }
+        key.push_str(word);
+        if let Some((log_prob, _)) = lookup(&key) {
+            return backoff + log_prob;
+        }
+        if !history.is_empty()
+            && let Some((_, bow)) = lookup(&history.join(" "))
+        {
+            backoff += bow;
+        }
+    }
+    backoff + unknown
+}
@@ -0,0 +1,192 @@
+use crate::converter::{Candidate, ConvertOptions, Decoder, Segment};
+
+/// 一次输入会话，用户可以从候选结果中逐段选择汉字
+///
+/// 选择的汉字会被确定下来，之后只解码剩余的拼音，并且把最后确定的词组作为上下文传给解码器，
+/// 如 HMM 会使用其中最后一个字作为转移概率的起点，n 元语法模型会把它作为第一个词组的上文
+pub struct Composition<'a, D> {
+    decoder: &'a D,
+    options: &'a ConvertOptions,
+    pinyins: Vec<String>,
+    /// 每次选择的汉字、消耗的拼音数量和其中最后一个词组
+    selections: Vec<(String, usize, String)>,
+    committed: String,
+    consumed: usize,
+}
+
+impl<'a, D: Decoder> Composition<'a, D> {
+    pub fn new(decoder: &'a D, options: &'a ConvertOptions, pinyins: Vec<String>) -> Self {
+        Self {
+            decoder,
+            options,
+            pinyins,
+            selections: Vec::new(),
+            committed: String::new(),
+            consumed: 0,
+        }
+    }
+
+    /// 剩余拼音的候选结果，候选结果中的拼音范围是相对于剩余拼音的
+    pub fn candidates(&self) -> Vec<Candidate> {
+        let remaining = self.remaining();
+        if remaining.is_empty() {
+            return Vec::new();
+        }
+        let pinyin_seq = remaining.iter().map(String::as_str).collect::<Vec<_>>();
+        self.decoder
+            .decode_with_context(self.last_word(), &pinyin_seq, self.options)
+    }
+
+    /// 选择候选结果中的一段，这一段必须从剩余拼音的第一个开始，返回是否选择成功
+    pub fn select(&mut self, segment: &Segment) -> bool {
+        let span = segment.span();
+        if span.start != 0 || span.is_empty() || span.end > self.remaining().len() {
+            return false;
+        }
+        self.commit(segment.hanzi(), span.end, segment.hanzi());
+        true
+    }
+
+    /// 选择整个候选结果，返回是否选择成功
+    pub fn select_candidate(&mut self, candidate: &Candidate) -> bool {
+        let mut end = 0;
+        let mut last_word = "";
+        for segment in candidate.segments() {
+            if segment.span().start != end {
+                return false;
+            }
+            end = segment.span().end;
+            last_word = segment.hanzi();
+        }
+        if end == 0 || end > self.remaining().len() {
+            return false;
+        }
+        self.commit(candidate.text(), end, last_word);
+        true
+    }
+
+    /// 撤销最后一次选择，返回撤销的汉字
+    pub fn undo(&mut self) -> Option<String> {
+        let (hanzi, consumed, _) = self.selections.pop()?;
+        self.committed.truncate(self.committed.len() - hanzi.len());
+        self.consumed -= consumed;
+        Some(hanzi)
+    }
+
+    /// 已经确定的文本
+    pub fn committed(&self) -> &str {
+        &self.committed
+    }
+
+    /// 已经消耗的拼音数量
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// 所有的拼音，包括已经消耗的
+    pub fn pinyins(&self) -> &[String] {
+        &self.pinyins
+    }
+
+    /// 还没有确定汉字的拼音
+    pub fn remaining(&self) -> &[String] {
+        &self.pinyins[self.consumed..]
+    }
+
+    /// 所有的拼音都已经确定了汉字
+    pub fn is_complete(&self) -> bool {
+        self.consumed == self.pinyins.len()
+    }
+
+    /// 最后确定的词组，还没有选择时是空字符串
+    pub fn last_word(&self) -> &str {
+        self.selections.last().map_or("", |(_, _, word)| word)
+    }
+
+    fn commit(&mut self, hanzi: &str, consumed: usize, last_word: &str) {
+        self.committed.push_str(hanzi);
+        self.consumed += consumed;
+        self.selections
+            .push((hanzi.to_owned(), consumed, last_word.to_owned()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::{Converter, Segment};
+    use pinyinchch_type::hmm::Hmm;
+
+    /// 单独输入 zhang 时 "张" 的起始概率更大，在 "市" 之后转移到 "长" 的概率更大
+    struct TestHmm;
+
+    impl Hmm for TestHmm {
+        fn start(&self, state: &str) -> f64 {
+            match state {
+                "很" => 0.5,
+                "张" => 0.4,
+                _ => 0.1,
+            }
+        }
+
+        fn emission(&self, _state: &str, _observation: &str) -> f64 {
+            1.0
+        }
+
+        fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+            match (from_state, to_state) {
+                ("市", "长") => 0.9,
+                ("市", "张") => 0.1,
+                ("很", "好") => 0.9,
+                _ => 0.1,
+            }
+        }
+
+        fn get_states(&self, observation: &str) -> Vec<String> {
+            let states: &[&str] = match observation {
+                "shi" => &["市", "是"],
+                "zhang" => &["张", "长"],
+                "hen" => &["很"],
+                "hao" => &["好"],
+                _ => &[],
+            };
+            states.iter().map(|state| state.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn test_composition() {
+        let converter = Converter::hmm(TestHmm).path_num(3).build();
+        let mut composition = converter.compose("shizhanghenhao");
+        assert_eq!(composition.pinyins(), ["shi", "zhang", "hen", "hao"]);
+
+        // 只能选择从第一个拼音开始的一段
+        assert!(!composition.select(&Segment::new("长", 1..2, 0.9)));
+        assert!(composition.select(&Segment::new("市", 0..1, 0.1)));
+        assert_eq!(composition.committed(), "市");
+        assert_eq!(composition.remaining(), ["zhang", "hen", "hao"]);
+
+        assert_eq!(converter.convert("zhang")[0].text(), "张");
+        // 已经确定的 "市" 作为转移概率的起点
+        let candidates = composition.candidates();
+        assert_eq!(candidates[0].text(), "长很好");
+        let first = candidates[0].segments()[0].clone();
+        assert_eq!(first.span(), 0..1);
+        assert!(composition.select(&first));
+        assert_eq!(composition.consumed(), 2);
+
+        let candidates = composition.candidates();
+        assert!(composition.select_candidate(&candidates[0]));
+        assert_eq!(composition.committed(), "市长很好");
+        assert_eq!(
+            composition.last_word(),
+            candidates[0].segments().last().unwrap().hanzi()
+        );
+        assert!(composition.is_complete());
+        assert!(composition.candidates().is_empty());
+
+        assert_eq!(composition.undo(), Some("很好".to_owned()));
+        assert_eq!(composition.remaining(), ["hen", "hao"]);
+        assert_eq!(composition.committed(), "市长");
+        assert_eq!(composition.last_word(), "长");
+    }
+}
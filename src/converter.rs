@@ -0,0 +1,1044 @@
+use crate::composition::Composition;
+#[cfg(all(feature = "hmm", feature = "dag"))]
+use crate::hybrid::HybridDecoder;
+use crate::incremental::{IncrementalDecode, IncrementalDecoder};
+#[cfg(feature = "ngram")]
+use crate::ngram::NgramDecoder;
+use crate::pinyin::{
+    AsciiPolicy, Chunk, DEFAULT_SEPARATORS, FullWidthPunct, FuzzyRules, FuzzyTokenizer,
+    PinyinLattice, Token, pinyin_lattice, pinyin_split, pinyin_tokenize, pinyin_tokenize_mixed,
+    pinyin_tokenize_toned, plain_lowercase, split_paragraph_with_policy,
+};
+use crate::priority::Item;
+#[cfg(feature = "dag")]
+use pinyinchch_type::dag::Dag;
+#[cfg(feature = "hmm")]
+use pinyinchch_type::hmm::Hmm;
+#[cfg(feature = "ngram")]
+use pinyinchch_type::ngram::LanguageModel;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// 默认的最小概率，防止概率为0
+pub const DEFAULT_MIN_PROB: f64 = 3.14e-200;
+
+/// [`Decoder::decode_lattice`] 的默认实现最多解码的切分方式数量
+pub const MAX_LATTICE_SEGMENTATIONS: usize = 16;
+
+/// 候选结果中的一段，HMM 中是单个汉字，DAG 中可能是词组
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    hanzi: String,
+    span: Range<usize>,
+    prob: f64,
+}
+
+impl Segment {
+    pub fn new(hanzi: impl Into<String>, span: Range<usize>, prob: f64) -> Self {
+        Segment {
+            hanzi: hanzi.into(),
+            span,
+            prob,
+        }
+    }
+
+    pub fn hanzi(&self) -> &str {
+        &self.hanzi
+    }
+
+    /// 这一段对应的拼音在输入拼音序列中的下标范围
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// 这一段的局部概率，HMM 中是转移概率（或起始概率）和发射概率的乘积，DAG 中是词组的概率，
+    /// 混合解码和语言模型解码中也是 DAG 中的词组概率，其它模型的概率只计入候选结果的分数
+    pub fn prob(&self) -> f64 {
+        self.prob
+    }
+}
+
+/// 转换得到的候选结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    text: String,
+    pinyins: Vec<String>,
+    segments: Vec<Segment>,
+    score: f64,
+}
+
+impl Candidate {
+    pub fn new(pinyins: Vec<String>, segments: Vec<Segment>, score: f64) -> Self {
+        Candidate {
+            text: segments.iter().map(Segment::hanzi).collect(),
+            pinyins,
+            segments,
+            score,
+        }
+    }
+
+    /// 从解码结果创建候选结果，路径没有记录拼音时使用输入的拼音序列
+    pub(crate) fn from_item(item: Item, pinyin_seq: &[&str]) -> Self {
+        let segments = item
+            .path()
+            .iter()
+            .zip(item.spans())
+            .map(|(hanzi, (span, prob))| Segment::new(hanzi.clone(), span.clone(), *prob))
+            .collect();
+        let pinyins = if item.pinyins().is_empty() {
+            pinyin_seq.iter().map(|pinyin| pinyin.to_string()).collect()
+        } else {
+            item.pinyins().clone()
+        };
+        Candidate::new(pinyins, segments, item.score())
+    }
+
+    /// 拼接之后的汉字
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// 结果对应的拼音序列，`Segment::span` 是这个序列的下标范围
+    ///
+    /// 在拼音网格上解码时，不同的结果可能使用不同的拼音切分
+    pub fn pinyins(&self) -> &[String] {
+        &self.pinyins
+    }
+
+    /// 组成结果的每一段，按拼音的顺序排列
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// 结果覆盖的拼音范围，只覆盖前面几个拼音的候选结果（见 [`Converter::convert_prefixes`]）的范围比 `pinyins` 短
+    pub fn span(&self) -> Range<usize> {
+        match (self.segments.first(), self.segments.last()) {
+            (Some(first), Some(last)) => first.span().start..last.span().end,
+            _ => 0..0,
+        }
+    }
+
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+}
+
+/// 拼音字符串的切分方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// 使用前缀树切分，会跳过不是拼音的字符，见 [`pinyin_tokenize`]
+    #[default]
+    Trie,
+    /// 要求整个字符串都是有效的拼音，有多种切分方式时使用第一种，见 [`pinyin_split`]
+    Split,
+    /// 要求整个字符串都是有效的拼音，在拼音网格上同时考虑所有的切分方式，见 [`pinyin_lattice`]
+    Lattice,
+}
+
+impl Tokenizer {
+    fn tokenize(&self, pinyin: &str) -> Vec<String> {
+        match self {
+            Tokenizer::Trie => pinyin_tokenize(pinyin),
+            Tokenizer::Split | Tokenizer::Lattice => pinyin_split(pinyin)
+                .first()
+                .map(|split| split.split(' ').map(str::to_owned).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// 转换时的打分参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertOptions {
+    /// 返回路径数量
+    pub path_num: usize,
+    /// 是否使用对数概率
+    pub use_log_prob: bool,
+    /// 最小概率值，防止概率为0，只有 HMM 会使用
+    pub min_prob: f64,
+    /// 限制搜索范围和时间，默认不限制
+    pub decode: DecodeOptions,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            path_num: 5,
+            use_log_prob: true,
+            min_prob: DEFAULT_MIN_PROB,
+            decode: DecodeOptions::default(),
+        }
+    }
+}
+
+/// 输入很长时限制解码的搜索范围和时间，每一项为 None 时不限制
+///
+/// 超过时间限制时不再计算之后的拼音，返回已经计算完的部分结果，
+/// 这时候选结果只覆盖前面的拼音，可以通过 [`Candidate::span`] 判断
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeOptions {
+    /// 每个位置最多保留的部分结果数量，HMM 中是状态的数量，DAG 中是路径的数量
+    pub beam_width: Option<usize>,
+    /// 一个词组最多包含的拼音数量，HMM 不会使用
+    pub max_phrase_len: Option<usize>,
+    /// 每个位置最多考虑的状态数量，按照发射概率选择，只有 HMM 会使用
+    pub max_states: Option<usize>,
+    /// 每次解码的时间限制
+    pub time_limit: Option<Duration>,
+}
+
+impl DecodeOptions {
+    /// 从现在开始计算的截止时间
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.time_limit.map(|limit| Instant::now() + limit)
+    }
+
+    /// 时间限制改为到 deadline 为止的剩余时间，多次解码共用同一个截止时间
+    pub(crate) fn until(&self, deadline: Option<Instant>) -> Self {
+        Self {
+            time_limit: deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())),
+            ..self.clone()
+        }
+    }
+
+    /// 是否已经超过截止时间
+    pub(crate) fn expired(deadline: Option<Instant>) -> bool {
+        deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// 在 to_idx 结束的词组最早的起点
+    #[cfg(feature = "dag")]
+    pub(crate) fn first_phrase_start(&self, to_idx: usize) -> usize {
+        self.max_phrase_len
+            .map_or(0, |max_len| (to_idx + 1).saturating_sub(max_len))
+    }
+}
+
+/// 将拼音序列解码成候选结果
+pub trait Decoder {
+    fn decode(&self, pinyin_seq: &[&str], options: &ConvertOptions) -> Vec<Candidate>;
+
+    /// 在已经确定的文本之后解码，context 是最后确定的一个词组，默认忽略 context
+    fn decode_with_context(
+        &self,
+        context: &str,
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate> {
+        let _ = context;
+        self.decode(pinyin_seq, options)
+    }
+
+    /// 在拼音网格上解码，默认按照 [`PinyinLattice::iter_segmentations`] 的顺序对切分方式分别解码，再合并结果
+    ///
+    /// 最多解码 `beam_width` 种切分方式，没有设置时最多 [`MAX_LATTICE_SEGMENTATIONS`] 种；
+    /// 所有的切分方式共用一个时间限制，超过时间限制之后不再解码剩下的切分方式。
+    /// 内置的解码器都直接在网格上动态规划，不使用这个默认实现
+    fn decode_lattice(&self, lattice: &PinyinLattice, options: &ConvertOptions) -> Vec<Candidate> {
+        let deadline = options.decode.deadline();
+        let max_segmentations = options
+            .decode
+            .beam_width
+            .unwrap_or(MAX_LATTICE_SEGMENTATIONS);
+        let mut candidates = Vec::new();
+        for pinyin_seq in lattice.iter_segmentations().take(max_segmentations) {
+            let options = ConvertOptions {
+                decode: options.decode.until(deadline),
+                ..options.clone()
+            };
+            candidates.extend(self.decode(&pinyin_seq, &options));
+            if DecodeOptions::expired(deadline) {
+                break;
+            }
+        }
+        candidates.sort_by(|a, b| {
+            b.score()
+                .partial_cmp(&a.score())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut seen = HashSet::new();
+        candidates.retain(|candidate| seen.insert(candidate.text().to_owned()));
+        candidates.truncate(options.path_num);
+        candidates
+    }
+}
+
+/// 使用 Viterbi 算法解码
+#[cfg(feature = "hmm")]
+pub struct HmmDecoder<H>(pub H);
+
+/// 每次解码只获取一次学习记录的读锁，见 [`LearnedHmm::snapshot`](crate::hmm::LearnedHmm::snapshot)，
+/// 使用 `Converter::builder(learned_hmm)` 创建转换器
+#[cfg(feature = "hmm")]
+impl<H: Hmm> Decoder for crate::hmm::LearnedHmm<H> {
+    fn decode(&self, pinyin_seq: &[&str], options: &ConvertOptions) -> Vec<Candidate> {
+        HmmDecoder(self.snapshot()).decode(pinyin_seq, options)
+    }
+
+    fn decode_with_context(
+        &self,
+        context: &str,
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate> {
+        HmmDecoder(self.snapshot()).decode_with_context(context, pinyin_seq, options)
+    }
+
+    fn decode_lattice(&self, lattice: &PinyinLattice, options: &ConvertOptions) -> Vec<Candidate> {
+        HmmDecoder(self.snapshot()).decode_lattice(lattice, options)
+    }
+}
+
+#[cfg(feature = "hmm")]
+impl<H: Hmm> Decoder for HmmDecoder<H> {
+    fn decode(&self, pinyin_seq: &[&str], options: &ConvertOptions) -> Vec<Candidate> {
+        crate::hmm::viterbi_with_options(
+            &self.0,
+            None,
+            pinyin_seq,
+            options.path_num,
+            options.use_log_prob,
+            options.min_prob,
+            &options.decode,
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, pinyin_seq))
+        .collect()
+    }
+
+    /// 使用 context 的最后一个字作为第一个拼音的转移概率的起点
+    fn decode_with_context(
+        &self,
+        context: &str,
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate> {
+        let last = context.chars().last().map(String::from);
+        crate::hmm::viterbi_with_options(
+            &self.0,
+            last.as_deref(),
+            pinyin_seq,
+            options.path_num,
+            options.use_log_prob,
+            options.min_prob,
+            &options.decode,
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, pinyin_seq))
+        .collect()
+    }
+
+    fn decode_lattice(&self, lattice: &PinyinLattice, options: &ConvertOptions) -> Vec<Candidate> {
+        crate::hmm::viterbi_lattice_with_options(
+            &self.0,
+            lattice,
+            options.path_num,
+            options.use_log_prob,
+            options.min_prob,
+            &options.decode,
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, &[]))
+        .collect()
+    }
+}
+
+/// 使用动态规划算法解码，切分不同但汉字相同的路径只保留得分最高的一个
+#[cfg(feature = "dag")]
+pub struct DagDecoder<D>(pub D);
+
+#[cfg(feature = "dag")]
+impl<D: Dag> Decoder for DagDecoder<D> {
+    fn decode(&self, pinyin_seq: &[&str], options: &ConvertOptions) -> Vec<Candidate> {
+        crate::dag::dispatch_with_edge(
+            &self.0,
+            pinyin_seq,
+            options.path_num,
+            options.use_log_prob,
+            &options.decode,
+            true,
+            |_, _, prob| prob.ln(),
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, pinyin_seq))
+        .collect()
+    }
+
+    fn decode_lattice(&self, lattice: &PinyinLattice, options: &ConvertOptions) -> Vec<Candidate> {
+        crate::dag::dispatch_lattice_with_options(
+            &self.0,
+            lattice,
+            options.path_num,
+            options.use_log_prob,
+            &options.decode,
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, &[]))
+        .collect()
+    }
+}
+
+/// 将拼音字符串转换成汉字，封装了拼音切分和解码
+pub struct Converter<D> {
+    decoder: D,
+    tokenizer: Tokenizer,
+    fuzzy_tokenizer: Option<FuzzyTokenizer>,
+    options: ConvertOptions,
+    ascii_policy: AsciiPolicy,
+}
+
+#[cfg(feature = "hmm")]
+impl<H: Hmm> Converter<HmmDecoder<H>> {
+    /// 使用 HMM 模型
+    pub fn hmm(hmm: H) -> ConverterBuilder<HmmDecoder<H>> {
+        ConverterBuilder::new(HmmDecoder(hmm))
+    }
+}
+
+#[cfg(all(feature = "hmm", feature = "dag"))]
+impl<D: Dag, H: Hmm> Converter<HybridDecoder<D, H>> {
+    /// 同时使用 DAG 和 HMM 模型，使用默认的插值权重，见 [`HybridDecoder`]
+    pub fn hybrid(dag: D, hmm: H) -> ConverterBuilder<HybridDecoder<D, H>> {
+        ConverterBuilder::new(HybridDecoder::new(dag, hmm))
+    }
+}
+
+#[cfg(feature = "ngram")]
+impl<D: Dag, L: LanguageModel> Converter<NgramDecoder<D, L>> {
+    /// 在 DAG 的词组上使用词语级别的语言模型，见 [`NgramDecoder`]
+    pub fn ngram(dag: D, lm: L) -> ConverterBuilder<NgramDecoder<D, L>> {
+        ConverterBuilder::new(NgramDecoder::new(dag, lm))
+    }
+}
+
+#[cfg(feature = "dag")]
+impl<D: Dag> Converter<DagDecoder<D>> {
+    /// 使用 DAG 模型
+    pub fn dag(dag: D) -> ConverterBuilder<DagDecoder<D>> {
+        ConverterBuilder::new(DagDecoder(dag))
+    }
+
+    /// 把简拼（如 bj、beij）转换成汉字，`Candidate::pinyins` 返回匹配到的完整拼音
+    pub fn convert_abbreviated(&self, pinyin: impl AsRef<str>) -> Vec<Candidate> {
+        let pinyin_seq = crate::pinyin::pinyin_tokenize_abbreviated(pinyin);
+        crate::dag::dispatch_abbreviated_with_options(
+            &self.decoder.0,
+            &pinyin_seq,
+            self.options.path_num,
+            self.options.use_log_prob,
+            &self.options.decode,
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, &[]))
+        .collect()
+    }
+
+    /// 输入法候选窗口使用的结果，包括整句的结果和只覆盖前面几个拼音的词组，见 [`dispatch_prefixes`](crate::dag::dispatch_prefixes)
+    ///
+    /// `Candidate::span` 是结果覆盖的拼音范围，可以直接传给 [`Composition::select_candidate`]
+    pub fn convert_prefixes(&self, pinyin: impl AsRef<str>) -> Vec<Candidate> {
+        let pinyins = self.tokenize(pinyin.as_ref());
+        let pinyin_seq = pinyins.iter().map(String::as_str).collect::<Vec<_>>();
+        crate::dag::dispatch_prefixes_with_options(
+            &self.decoder.0,
+            &pinyin_seq,
+            self.options.path_num,
+            self.options.use_log_prob,
+            &self.options.decode,
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, &pinyin_seq))
+        .collect()
+    }
+}
+
+impl<D: Decoder> Converter<D> {
+    /// 使用自定义的解码器
+    pub fn builder(decoder: D) -> ConverterBuilder<D> {
+        ConverterBuilder::new(decoder)
+    }
+
+    /// 切分拼音字符串，然后转换成按分数降序排列的候选结果
+    pub fn convert(&self, pinyin: impl AsRef<str>) -> Vec<Candidate> {
+        if self.tokenizer == Tokenizer::Lattice {
+            let lattice = pinyin_lattice(pinyin);
+            return self.decoder.decode_lattice(&lattice, &self.options);
+        }
+        let pinyins = self.tokenize(pinyin.as_ref());
+        let pinyin_seq = pinyins.iter().map(String::as_str).collect::<Vec<_>>();
+        self.convert_seq(&pinyin_seq)
+    }
+
+    /// 切分带声调的拼音字符串（如 lv3you2、lǚyóu），声调会以数字的形式保留在拼音中，如 lv3
+    ///
+    /// 解码器需要能够处理带声调的拼音，如 [`TonedHmm`](crate::hmm::TonedHmm) 和 [`TonedDag`](crate::dag::TonedDag)
+    pub fn convert_toned(&self, pinyin: impl AsRef<str>) -> Vec<Candidate> {
+        let pinyins = pinyin_tokenize_toned(pinyin)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let pinyin_seq = pinyins.iter().map(String::as_str).collect::<Vec<_>>();
+        self.convert_seq(&pinyin_seq)
+    }
+
+    /// 转换已经切分好的拼音序列
+    pub fn convert_seq(&self, pinyin_seq: &[&str]) -> Vec<Candidate> {
+        if pinyin_seq.is_empty() {
+            return Vec::new();
+        }
+        self.decoder.decode(pinyin_seq, &self.options)
+    }
+
+    /// 转换一段包含标点、数字和其它文字的拼音，如 `"wo men jin tian qu bei jing, ran hou"` => `"我们今天去北京，然后"`
+    ///
+    /// 段落按照 [`split_paragraph_with_policy`] 和 [`ConverterBuilder::ascii_policy`] 设置的策略切分，
+    /// 每一段拼音分别转换并使用分数最高的结果，没有结果时保留原来的拼音，
+    /// 超过时间限制时结果没有覆盖的拼音也保留原来的文字；
+    /// 标点转换成全角的中文标点，连续的 `.` 转换成省略号 `……`，其它文字原样保留
+    pub fn convert_paragraph(&self, text: impl AsRef<str>) -> String {
+        let chunks = split_paragraph_with_policy(text.as_ref(), self.ascii_policy);
+        let converted = chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Pinyin(pinyin) => self.convert_chunk(pinyin),
+                _ => None,
+            })
+            .collect();
+        join_paragraph(&chunks, converted)
+    }
+
+    /// 和 [`Converter::convert_paragraph`] 相同，但是使用多个线程同时转换每一段拼音
+    pub fn convert_paragraph_parallel(&self, text: impl AsRef<str>) -> String
+    where
+        D: Sync,
+    {
+        let chunks = split_paragraph_with_policy(text.as_ref(), self.ascii_policy);
+        let pinyin_chunks = chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, chunk)| match chunk {
+                Chunk::Pinyin(pinyin) => Some((idx, *pinyin)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let workers = std::thread::available_parallelism()
+            .map_or(1, usize::from)
+            .min(pinyin_chunks.len())
+            .max(1);
+
+        let mut converted = vec![None; chunks.len()];
+        std::thread::scope(|scope| {
+            let handles = (0..workers)
+                .map(|worker| {
+                    let pinyin_chunks = &pinyin_chunks;
+                    scope.spawn(move || {
+                        pinyin_chunks
+                            .iter()
+                            .skip(worker)
+                            .step_by(workers)
+                            .map(|(idx, pinyin)| (*idx, self.convert_chunk(pinyin)))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            for handle in handles {
+                // 转换时 panic 的话在当前线程继续 panic
+                let results = handle
+                    .join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err));
+                for (idx, text) in results {
+                    converted[idx] = text;
+                }
+            }
+        });
+        join_paragraph(&chunks, converted)
+    }
+
+    /// 转换混合了拼音、英文、数字、标点和汉字的文本，如 `"wo xiang mai iPhone 15"` => `"我想买iPhone 15"`
+    ///
+    /// 文本按照 [`pinyin_tokenize_mixed`] 切分，连续的拼音（中间只有空白字符或分隔符）一起转换并使用分数最高的结果，
+    /// 拼音之间的空白字符会被去掉，没有结果时保留原来的拼音，超过时间限制时结果没有覆盖的拼音也保留原来的文字；
+    /// 其它词元原样保留。
+    /// 使用 [`ConverterBuilder::ascii_policy`] 设置的策略判断一个词是拼音还是英文
+    pub fn convert_mixed(&self, text: impl AsRef<str>) -> String {
+        let text = text.as_ref();
+        let tokens = pinyin_tokenize_mixed(text, self.ascii_policy);
+        let mut result = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < tokens.len() {
+            if !tokens[i].is_pinyin() {
+                result.push_str(tokens[i].text());
+                i += 1;
+                continue;
+            }
+            let mut end = i;
+            for (j, token) in tokens.iter().enumerate().skip(i + 1) {
+                match token {
+                    Token::Pinyin(..) => end = j,
+                    Token::Whitespace(..) => {}
+                    Token::Punct(_, punct)
+                        if punct.chars().all(|ch| DEFAULT_SEPARATORS.contains(&ch)) => {}
+                    _ => break,
+                }
+            }
+            let pinyin_tokens = tokens[i..=end]
+                .iter()
+                .filter(|token| token.is_pinyin())
+                .collect::<Vec<_>>();
+            let pinyin_seq = pinyin_tokens
+                .iter()
+                .map(|token| plain_lowercase(token.text()))
+                .collect::<Vec<_>>();
+            let pinyin_seq = pinyin_seq.iter().map(String::as_str).collect::<Vec<_>>();
+            match self.convert_seq(&pinyin_seq).into_iter().next() {
+                Some(candidate) => {
+                    result.push_str(candidate.text());
+                    // 超过时间限制时结果只覆盖前面的拼音，剩下的拼音保留原来的文字
+                    if let Some(rest) = pinyin_tokens.get(candidate.span().end) {
+                        result.push_str(&text[rest.range().start..tokens[end].range().end]);
+                    }
+                }
+                None => tokens[i..=end]
+                    .iter()
+                    .for_each(|token| result.push_str(token.text())),
+            }
+            i = end + 1;
+        }
+        result
+    }
+
+    /// 转换段落中的一段拼音，空白字符作为强制的切分位置
+    fn convert_chunk(&self, pinyin: &str) -> Option<String> {
+        let joined = pinyin
+            .split_whitespace()
+            .map(plain_lowercase)
+            .collect::<Vec<_>>()
+            .join("'");
+        let candidate = self.convert(joined).into_iter().next()?;
+        // 超过时间限制时结果只覆盖前面的拼音，剩下的拼音保留原来的文字
+        let covered = candidate.pinyins()[..candidate.span().end]
+            .iter()
+            .map(|pinyin| pinyin.chars().count())
+            .sum();
+        let mut text = candidate.text().to_owned();
+        text.push_str(skip_letters(pinyin, covered));
+        Some(text)
+    }
+
+    /// 按照设置的切分方式和模糊音规则切分拼音字符串
+    fn tokenize(&self, pinyin: &str) -> Vec<String> {
+        match (&self.tokenizer, &self.fuzzy_tokenizer) {
+            (Tokenizer::Trie, Some(fuzzy_tokenizer)) => fuzzy_tokenizer.tokenize(pinyin),
+            (tokenizer, _) => tokenizer.tokenize(pinyin),
+        }
+    }
+
+    /// 开始一次输入会话，可以逐段选择候选结果，见 [`Composition`]
+    pub fn compose(&self, pinyin: impl AsRef<str>) -> Composition<'_, D> {
+        let pinyins = self.tokenize(pinyin.as_ref());
+        Composition::new(&self.decoder, &self.options, pinyins)
+    }
+
+    /// 转换成增量解码器，使用相同的解码器和打分参数
+    ///
+    /// 增量解码器只重新切分输入末尾不稳定的部分，总是使用前缀树切分拼音，和 [`Tokenizer::Trie`] 相同。
+    /// 设置的 [`Tokenizer::Split`]、[`Tokenizer::Lattice`] 和 [`ConverterBuilder::fuzzy_rules`] 不会生效
+    pub fn into_incremental(self) -> IncrementalDecoder<D>
+    where
+        D: IncrementalDecode,
+    {
+        IncrementalDecoder::new(self.decoder, self.options)
+    }
+
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    pub fn options(&self) -> &ConvertOptions {
+        &self.options
+    }
+}
+
+/// 拼接段落的转换结果，converted 中没有结果的拼音保留原来的文字
+fn join_paragraph(chunks: &[Chunk], converted: Vec<Option<String>>) -> String {
+    let is_dot = |(chunk, converted): &(&Chunk, Option<String>)| {
+        matches!(chunk, Chunk::Punct('.')) && converted.is_none()
+    };
+    let mut punct = FullWidthPunct::new();
+    let mut result = String::new();
+    let mut chunks = chunks.iter().zip(converted).peekable();
+    while let Some((chunk, converted)) = chunks.next() {
+        match (chunk, converted) {
+            (_, Some(text)) => result.push_str(&text),
+            (Chunk::Pinyin(text) | Chunk::Text(text), None) => result.push_str(text),
+            // 连续的 . 是省略号
+            (Chunk::Punct('.'), None) if chunks.peek().is_some_and(is_dot) => {
+                while chunks.next_if(is_dot).is_some() {}
+                result.push_str("……");
+            }
+            (Chunk::Punct(ch), None) => result.push(punct.convert(*ch)),
+        }
+    }
+    result
+}
+
+/// 跳过 text 中的 count 个拼音字母，返回剩下的文字，空白字符和分隔符不计数
+fn skip_letters(text: &str, count: usize) -> &str {
+    let mut count = count;
+    for (idx, ch) in text.char_indices() {
+        if ch.is_whitespace() || DEFAULT_SEPARATORS.contains(&ch) {
+            continue;
+        }
+        if count == 0 {
+            return &text[idx..];
+        }
+        count -= 1;
+    }
+    ""
+}
+
+/// [`Converter`] 的构建器
+pub struct ConverterBuilder<D> {
+    decoder: D,
+    tokenizer: Tokenizer,
+    fuzzy_tokenizer: Option<FuzzyTokenizer>,
+    options: ConvertOptions,
+    ascii_policy: AsciiPolicy,
+}
+
+impl<D: Decoder> ConverterBuilder<D> {
+    pub fn new(decoder: D) -> Self {
+        ConverterBuilder {
+            decoder,
+            tokenizer: Tokenizer::default(),
+            fuzzy_tokenizer: None,
+            options: ConvertOptions::default(),
+            ascii_policy: AsciiPolicy::default(),
+        }
+    }
+
+    pub fn tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// 使用 [`Tokenizer::Trie`] 时按照模糊音规则切分拼音，见 [`FuzzyTokenizer`]
+    ///
+    /// 切分出的模糊音不一定是有效的拼音，解码器需要使用相同的规则展开，如 [`FuzzyDag`](crate::dag::FuzzyDag)
+    /// 和 [`FuzzyHmm`](crate::hmm::FuzzyHmm)；[`Tokenizer::Split`] 和 [`Tokenizer::Lattice`] 不使用模糊音规则
+    pub fn fuzzy_rules(mut self, rules: &FuzzyRules) -> Self {
+        self.fuzzy_tokenizer = Some(FuzzyTokenizer::new(rules));
+        self
+    }
+
+    pub fn options(mut self, options: ConvertOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn path_num(mut self, path_num: usize) -> Self {
+        self.options.path_num = path_num;
+        self
+    }
+
+    pub fn use_log_prob(mut self, use_log_prob: bool) -> Self {
+        self.options.use_log_prob = use_log_prob;
+        self
+    }
+
+    pub fn min_prob(mut self, min_prob: f64) -> Self {
+        self.options.min_prob = min_prob;
+        self
+    }
+
+    pub fn decode_options(mut self, decode: DecodeOptions) -> Self {
+        self.options.decode = decode;
+        self
+    }
+
+    /// 转换段落和混合文本时判断一个词是拼音还是英文的策略，默认为 [`AsciiPolicy::FullCoverage`]
+    pub fn ascii_policy(mut self, ascii_policy: AsciiPolicy) -> Self {
+        self.ascii_policy = ascii_policy;
+        self
+    }
+
+    pub fn build(self) -> Converter<D> {
+        Converter {
+            decoder: self.decoder,
+            tokenizer: self.tokenizer,
+            fuzzy_tokenizer: self.fuzzy_tokenizer,
+            options: self.options,
+            ascii_policy: self.ascii_policy,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "dag"))]
+mod tests {
+    use super::{Converter, Segment, Tokenizer};
+    use crate::pinyin::AsciiPolicy;
+    use pinyinchch_type::dag::Dag;
+
+    struct TestDag;
+
+    impl Dag for TestDag {
+        fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+            let phrases = match pinyin_list.join(",").as_str() {
+                "ni" => vec![("你".to_owned(), 0.8), ("泥".to_owned(), 0.2)],
+                "hao" => vec![("好".to_owned(), 0.7), ("号".to_owned(), 0.3)],
+                "ni,hao" => vec![("你好".to_owned(), 0.9)],
+                "ji,nan" => vec![("济南".to_owned(), 0.6)],
+                "jin" => vec![("今".to_owned(), 0.5)],
+                "an" => vec![("安".to_owned(), 0.5)],
+                _ => Vec::new(),
+            };
+            phrases.into_iter().take(num).collect()
+        }
+    }
+
+    #[test]
+    fn test_converter() {
+        let converter = Converter::dag(TestDag).path_num(2).build();
+        let candidates = converter.convert("nihao");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].text(), "你好");
+        assert_eq!(candidates[0].segments(), [Segment::new("你好", 0..2, 0.9)]);
+        // 你 + 好 和 你好 的汉字相同，只保留得分更高的 你好
+        assert_eq!(
+            candidates[1].segments(),
+            [Segment::new("你", 0..1, 0.8), Segment::new("号", 1..2, 0.3)]
+        );
+        assert!(candidates[0].score() > candidates[1].score());
+
+        let converter = Converter::dag(TestDag).tokenizer(Tokenizer::Split).build();
+        assert!(converter.convert("ni hao!").is_empty());
+        assert!(converter.convert("").is_empty());
+    }
+
+    #[test]
+    fn test_decode_options() {
+        use super::DecodeOptions;
+        use std::time::Duration;
+
+        // 词组最多一个拼音时只能逐字转换
+        let options = DecodeOptions {
+            max_phrase_len: Some(1),
+            ..Default::default()
+        };
+        let converter = Converter::dag(TestDag).decode_options(options).build();
+        let candidates = converter.convert("nihao");
+        assert_eq!(candidates[0].text(), "你好");
+        assert_eq!(candidates[0].segments().len(), 2);
+
+        // 每个位置只保留一条路径
+        let options = DecodeOptions {
+            beam_width: Some(1),
+            ..Default::default()
+        };
+        let converter = Converter::dag(TestDag).decode_options(options).build();
+        assert_eq!(converter.convert("nihao").len(), 1);
+
+        // 超时之后返回已经计算完的部分结果
+        let options = DecodeOptions {
+            time_limit: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let converter = Converter::dag(TestDag).decode_options(options).build();
+        let candidates = converter.convert("nihao");
+        assert_eq!(candidates[0].text(), "你");
+        assert_eq!(candidates[0].span(), 0..1);
+        // 没有覆盖的拼音保留原来的文字
+        assert_eq!(
+            converter.convert_paragraph("ni Hao ma, ni hao"),
+            "你Hao ma，你hao"
+        );
+        assert_eq!(
+            converter.convert_paragraph_parallel("ni Hao ma, ni hao"),
+            "你Hao ma，你hao"
+        );
+        assert_eq!(
+            converter.convert_mixed("ni Hao ma, ni hao 3"),
+            "你Hao ma, 你hao 3"
+        );
+    }
+
+    #[test]
+    fn test_converter_paragraph() {
+        let converter = Converter::dag(TestDag).build();
+        let text = "Ni hao, ji nan! 3.5 hello \"ni\" zzz";
+        let expected = "你好，济南！3.5 hello“你”zzz";
+        assert_eq!(converter.convert_paragraph(text), expected);
+        assert_eq!(converter.convert_paragraph_parallel(text), expected);
+        // 没有结果的拼音保留原来的文字
+        assert_eq!(converter.convert_paragraph("ni hao ma?"), "ni hao ma？");
+        // 连续的 . 是省略号，单独的 . 是句号
+        assert_eq!(converter.convert_paragraph("ni hao... ni."), "你好……你。");
+        assert_eq!(converter.convert_paragraph_parallel(""), "");
+    }
+
+    #[test]
+    fn test_converter_mixed() {
+        let converter = Converter::dag(TestDag).build();
+        assert_eq!(
+            converter.convert_mixed("Ni hao,ji'nan 3.5 hello 你好 ni"),
+            "你好,济南 3.5 hello 你好 你"
+        );
+        // 没有结果的拼音保留原来的文字
+        assert_eq!(converter.convert_mixed("ni hao ma?"), "ni hao ma?");
+        // 带声调的拼音也可以转换
+        assert_eq!(converter.convert_mixed("Nǐ HǍO, 3.5"), "你好, 3.5");
+        assert_eq!(converter.convert_paragraph("Nǐ hǎo, nǐ."), "你好，你。");
+        assert_eq!(converter.convert_mixed(""), "");
+
+        // 完整覆盖时 nihaox 是英文，贪心切分时 nihao 是拼音
+        assert_eq!(converter.convert_mixed("nihaox"), "nihaox");
+        let converter = Converter::dag(TestDag)
+            .ascii_policy(AsciiPolicy::Greedy)
+            .build();
+        assert_eq!(converter.convert_mixed("nihaox"), "你好x");
+        let converter = Converter::dag(TestDag)
+            .ascii_policy(AsciiPolicy::Latin)
+            .build();
+        assert_eq!(converter.convert_mixed("ni hao"), "ni hao");
+    }
+
+    #[test]
+    fn test_converter_fuzzy() {
+        use crate::dag::FuzzyDag;
+        use crate::pinyin::{FuzzyRule, FuzzyRules};
+
+        // fao 不是有效的拼音，f/h 规则下切分成 fao，再由 FuzzyDag 展开成 hao
+        let rules = FuzzyRules::new().with_rule(FuzzyRule::FH);
+        let converter = Converter::dag(FuzzyDag::new(TestDag, rules.clone()))
+            .fuzzy_rules(&rules)
+            .build();
+        let candidates = converter.convert("nifao");
+        assert_eq!(candidates[0].text(), "你好");
+        assert_eq!(candidates[0].pinyins(), ["ni", "fao"]);
+        // 没有设置模糊音规则时 f 会被跳过，切分成 ni ao
+        let converter = Converter::dag(FuzzyDag::new(TestDag, rules)).build();
+        assert!(
+            converter
+                .convert("nifao")
+                .iter()
+                .all(|candidate| candidate.text() != "你好")
+        );
+    }
+
+    #[test]
+    fn test_converter_lattice() {
+        // 只有 ji,nan 能组成词组，jin an 的切分不会被选中
+        let converter = Converter::dag(TestDag)
+            .tokenizer(Tokenizer::Lattice)
+            .build();
+        let candidates = converter.convert("jinan");
+        assert_eq!(candidates[0].text(), "济南");
+        assert_eq!(candidates[0].pinyins(), ["ji", "nan"]);
+        assert_eq!(candidates[0].segments(), [Segment::new("济南", 0..2, 0.6)]);
+        assert!(
+            candidates
+                .iter()
+                .any(|candidate| candidate.text() == "今安")
+        );
+    }
+
+    #[test]
+    fn test_default_decode_lattice() {
+        use super::{Candidate, ConvertOptions, DecodeOptions, Decoder};
+
+        // 只实现 decode，使用默认的 decode_lattice
+        struct SeqDecoder;
+
+        impl Decoder for SeqDecoder {
+            fn decode(&self, pinyin_seq: &[&str], options: &ConvertOptions) -> Vec<Candidate> {
+                super::DagDecoder(TestDag).decode(pinyin_seq, options)
+            }
+        }
+
+        let converter = Converter::builder(SeqDecoder)
+            .tokenizer(Tokenizer::Lattice)
+            .build();
+        let candidates = converter.convert("jinan");
+        assert_eq!(candidates[0].text(), "济南");
+        assert!(
+            candidates
+                .iter()
+                .any(|candidate| candidate.text() == "今安")
+        );
+
+        // 只解码第一种切分方式，即最长匹配的 jin an
+        let converter = Converter::builder(SeqDecoder)
+            .tokenizer(Tokenizer::Lattice)
+            .decode_options(DecodeOptions {
+                beam_width: Some(1),
+                ..Default::default()
+            })
+            .build();
+        let candidates = converter.convert("jinan");
+        assert_eq!(candidates[0].text(), "今安");
+        assert!(
+            candidates
+                .iter()
+                .all(|candidate| candidate.text() != "济南")
+        );
+    }
+
+    #[test]
+    fn test_converter_abbreviated() {
+        let converter = Converter::dag(TestDag).build();
+        let candidates = converter.convert_abbreviated("jn");
+        assert_eq!(candidates[0].text(), "济南");
+        assert_eq!(candidates[0].pinyins(), ["ji", "nan"]);
+        let candidates = converter.convert_abbreviated("nih");
+        assert_eq!(candidates[0].text(), "你好");
+    }
+
+    #[test]
+    fn test_converter_prefixes() {
+        let converter = Converter::dag(TestDag).path_num(2).build();
+        let candidates = converter.convert_prefixes("nihao");
+        let texts = candidates
+            .iter()
+            .map(|candidate| candidate.text())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["你好", "你", "泥"]);
+        assert_eq!(candidates[0].span(), 0..2);
+        assert_eq!(candidates[1].span(), 0..1);
+        assert_eq!(candidates[1].pinyins(), ["ni", "hao"]);
+
+        // 选择只覆盖第一个拼音的词组之后，继续转换剩余的拼音
+        let mut composition = converter.compose("nihao");
+        assert!(composition.select_candidate(&candidates[2]));
+        assert_eq!(composition.remaining(), ["hao"]);
+        assert_eq!(composition.candidates()[0].text(), "好");
+
+        // 使用转换器的解码限制
+        let converter = Converter::dag(TestDag)
+            .path_num(2)
+            .decode_options(super::DecodeOptions {
+                max_phrase_len: Some(1),
+                ..Default::default()
+            })
+            .build();
+        let candidates = converter.convert_prefixes("nihao");
+        assert_eq!(candidates[0].text(), "你好");
+        assert_eq!(candidates[0].segments().len(), 2);
+        let candidates = converter.convert_abbreviated("nh");
+        assert_eq!(candidates[0].segments().len(), 2);
+    }
+
+    #[test]
+    fn test_converter_toned() {
+        use crate::dag::TonedDag;
+        use crate::pinyin::ToneTable;
+
+        let table = ToneTable::from_reader("你 ni3\n泥 ni2\n".as_bytes()).unwrap();
+        let converter = Converter::dag(TonedDag::new(TestDag, table)).build();
+        let candidates = converter.convert_toned("ni2");
+        assert_eq!(candidates[0].text(), "泥");
+        assert_eq!(candidates[0].pinyins(), ["ni2"]);
+        let candidates = converter.convert_toned("ni");
+        assert_eq!(candidates[0].text(), "你");
+    }
+}
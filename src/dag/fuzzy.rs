@@ -0,0 +1,71 @@
+use crate::pinyin::FuzzyRules;
+use pinyinchch_type::dag::Dag;
+use std::collections::HashMap;
+
+/// 一个拼音列表最多展开的模糊音组合数量，按照权重保留最大的几个
+pub const MAX_FUZZY_COMBINATIONS: usize = 64;
+
+/// 支持模糊音的 DAG，拼音列表中的每个拼音会先按照模糊音规则展开，再查询内部的 DAG
+///
+/// 词组的概率会乘以所有替换的惩罚系数，同一个词组取最大的概率
+#[derive(Debug, Clone)]
+pub struct FuzzyDag<D> {
+    dag: D,
+    rules: FuzzyRules,
+}
+
+impl<D: Dag> FuzzyDag<D> {
+    pub fn new(dag: D, rules: FuzzyRules) -> Self {
+        Self { dag, rules }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.dag
+    }
+
+    pub fn rules(&self) -> &FuzzyRules {
+        &self.rules
+    }
+
+    /// 所有模糊音组合的笛卡尔积，(拼音列表, 权重)
+    fn combinations(&self, pinyin_list: &[&str]) -> Vec<(Vec<String>, f64)> {
+        let mut combinations = vec![(Vec::new(), 1.0)];
+        for pinyin in pinyin_list {
+            let expanded = self.rules.expand(pinyin);
+            combinations = combinations
+                .iter()
+                .flat_map(|(prefix, weight)| {
+                    expanded.iter().map(move |(pinyin, pinyin_weight)| {
+                        let mut next = prefix.clone();
+                        next.push(pinyin.clone());
+                        (next, weight * pinyin_weight)
+                    })
+                })
+                .collect();
+            // 原来的拼音权重最大，排序之后总是排在第一个
+            combinations.sort_by(|a, b| b.1.total_cmp(&a.1));
+            combinations.truncate(MAX_FUZZY_COMBINATIONS);
+        }
+        combinations
+    }
+}
+
+impl<D: Dag> Dag for FuzzyDag<D> {
+    fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+        let mut best = HashMap::<String, f64>::new();
+        for (combination, weight) in self.combinations(pinyin_list) {
+            let combination = combination.iter().map(String::as_str).collect::<Vec<_>>();
+            for (phrase, prob) in self.dag.get_phrase(&combination, num) {
+                let prob = prob * weight;
+                let entry = best.entry(phrase).or_insert(prob);
+                if prob > *entry {
+                    *entry = prob;
+                }
+            }
+        }
+        let mut phrases = best.into_iter().collect::<Vec<_>>();
+        phrases.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        phrases.truncate(num);
+        phrases
+    }
+}
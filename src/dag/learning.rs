@@ -0,0 +1,63 @@
+use crate::converter::Candidate;
+use crate::learning::{self, LearningHistory, SharedHistory};
+use pinyinchch_type::dag::Dag;
+use std::cmp::Ordering;
+
+/// 根据用户的选择调整概率的 DAG
+///
+/// 选择过的词组的概率会按照 [`LearningHistory::weight`] 提升到同一个拼音下模型中最好的词组之上，
+/// 所以选择一次之后就会排在前面，模型中没有的词组也会作为候选结果
+#[derive(Debug, Clone)]
+pub struct LearnedDag<D> {
+    dag: D,
+    history: SharedHistory,
+}
+
+impl<D: Dag> LearnedDag<D> {
+    pub fn new(dag: D, history: SharedHistory) -> Self {
+        Self { dag, history }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.dag
+    }
+
+    pub fn history(&self) -> &SharedHistory {
+        &self.history
+    }
+
+    /// 记录一次选择，见 [`LearningHistory::record`]
+    pub fn record(&self, pinyins: &[&str], hanzi: &str) -> bool {
+        learning::write(&self.history).record(pinyins, hanzi)
+    }
+
+    /// 记录用户选择的候选结果，见 [`LearningHistory::record_candidate`]
+    pub fn record_candidate(&self, candidate: &Candidate) {
+        learning::write(&self.history).record_candidate(candidate);
+    }
+}
+
+impl<D: Dag> Dag for LearnedDag<D> {
+    fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+        let learned = learning::read(&self.history).phrases(pinyin_list);
+        if learned.is_empty() {
+            return self.dag.get_phrase(pinyin_list, num);
+        }
+        // 多取一些候选，选择过的词组提升之后可能排在前面
+        let mut phrases = self.dag.get_phrase(pinyin_list, num + learned.len());
+        let top = phrases.iter().map(|(_, prob)| *prob).fold(0.0, f64::max);
+        // 在最好的词组的基础上提升，权重越大、原来的概率越大，提升得越多
+        let boost = |prob: f64, weight: f64| {
+            LearningHistory::boost(top, weight * LearningHistory::boost(prob, weight))
+        };
+        for (phrase, weight) in learned {
+            match phrases.iter_mut().find(|(hanzi, _)| *hanzi == phrase) {
+                Some((_, prob)) => *prob = boost(*prob, weight),
+                None => phrases.push((phrase, boost(0.0, weight))),
+            }
+        }
+        phrases.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        phrases.truncate(num);
+        phrases
+    }
+}
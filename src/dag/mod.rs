@@ -1,5 +1,26 @@
-use crate::priority::{Item, PrioritySet};
+mod fuzzy;
+mod learning;
+mod reverse;
+mod tone;
+mod user;
+
+use crate::converter::DecodeOptions;
+pub use crate::dag::fuzzy::{FuzzyDag, MAX_FUZZY_COMBINATIONS};
+pub use crate::dag::learning::LearnedDag;
+pub use crate::dag::reverse::{HanziToPinyin, hanzi_to_pinyin};
+pub use crate::dag::tone::TonedDag;
+pub use crate::dag::user::{DEFAULT_USER_WEIGHT, UserDict};
+pub use crate::pinyin::DEFAULT_TONE_PENALTY;
+use crate::pinyin::{AbbreviatedPinyin, PinyinLattice};
+use crate::priority::{Item, PrioritySet, dedup_by_text};
 use pinyinchch_type::dag::Dag;
+use std::collections::HashMap;
+
+/// 在拼音网格上解码时，一个词组最多包含的拼音数量
+pub const MAX_LATTICE_PHRASE_LEN: usize = 8;
+
+/// 简拼解码时，一段拼音最多尝试的完整拼音组合数量
+pub const MAX_ABBREVIATION_COMBINATIONS: usize = 256;
 
 /// 使用动态规划算法，实现拼音转汉字
 ///
@@ -13,77 +34,583 @@ pub fn dispatch(
     pinyin_seq: &[&str],
     path_num: usize,
     use_log_prob: bool,
+) -> Vec<Item> {
+    dispatch_with_options(
+        dag,
+        pinyin_seq,
+        path_num,
+        use_log_prob,
+        &DecodeOptions::default(),
+    )
+}
+
+/// 使用动态规划算法，实现拼音转汉字，options 限制词组长度、每个位置保留的路径数量和时间
+///
+/// 超过时间限制时返回已经计算完的最后一个位置的结果，只覆盖前面的拼音
+///
+/// # Arguments
+/// * `dag` - DAG 实现
+/// * `pinyin_seq` - 拼音列表
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `options` - 搜索范围和时间的限制
+pub fn dispatch_with_options(
+    dag: &impl Dag,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+) -> Vec<Item> {
+    dispatch_with_edge(
+        dag,
+        pinyin_seq,
+        path_num,
+        use_log_prob,
+        options,
+        false,
+        |_, _, prob| prob.ln(),
+    )
+}
+
+/// 和 [`dispatch_with_options`] 相同，edge 计算一条边的对数分数
+///
+/// edge 的参数是之前的路径、这条边的词组和词组在 DAG 中的概率，返回值是这条边的对数分数，
+/// 混合解码和语言模型解码在这里加入词组之外的概率，`Item::spans` 中仍然记录词组在 DAG 中的概率。
+/// unique 为 true 时每个位置相同的汉字只保留得分最高的路径，返回的结果互不相同
+pub(crate) fn dispatch_with_edge(
+    dag: &impl Dag,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+    unique: bool,
+    edge: impl Fn(&Item, &str, f64) -> f64,
 ) -> Vec<Item> {
     if pinyin_seq.is_empty() {
         return Vec::new();
     }
     let pinyin_num = pinyin_seq.len();
+    let deadline = options.deadline();
 
-    // 创建动态规划数组
+    // dispatch_vec[i] 是覆盖前 i + 1 个拼音的最优路径
     let mut dispatch_vec = Vec::with_capacity(pinyin_num);
-    for _ in 0..pinyin_num {
-        dispatch_vec.push(PrioritySet::new(path_num));
+    for to_idx in 0..pinyin_num {
+        let (column, _) = dispatch_step_with_prefix(
+            dag,
+            &pinyin_seq[..to_idx + 1],
+            &dispatch_vec,
+            path_num,
+            use_log_prob,
+            options,
+            unique,
+            &edge,
+        );
+        dispatch_vec.push(column);
+        // 超过时间限制时返回已经计算完的部分结果
+        if DecodeOptions::expired(deadline) {
+            break;
+        }
     }
 
-    // 处理起始位置（from_idx = 0）
-    for from_idx in 0..1 {
-        for to_idx in from_idx..pinyin_num {
-            let slice = &pinyin_seq[from_idx..to_idx + 1];
+    // 获取最终结果
+    let mut result: Vec<Item> = dispatch_vec.last().unwrap().to_sorted_vec();
+    result.truncate(path_num);
+    result
+}
+
+/// 计算以 pinyin_seq 最后一个拼音结尾的最优路径，相同的汉字只保留得分最高的路径
+///
+/// dispatch_vec 是之前每个位置的结果，长度必须是 pinyin_seq.len() - 1
+pub(crate) fn dispatch_step(
+    dag: &impl Dag,
+    pinyin_seq: &[&str],
+    dispatch_vec: &[PrioritySet],
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+) -> PrioritySet {
+    dispatch_step_with_prefix(
+        dag,
+        pinyin_seq,
+        dispatch_vec,
+        path_num,
+        use_log_prob,
+        options,
+        true,
+        &|_, _, prob| prob.ln(),
+    )
+    .0
+}
+
+/// 和 [`dispatch_step`] 相同，同时返回从第一个拼音开始的词组，即 `get_phrase(pinyin_seq)` 的结果，
+/// edge 和 unique 见 [`dispatch_with_edge`]
+#[allow(clippy::too_many_arguments)]
+fn dispatch_step_with_prefix(
+    dag: &impl Dag,
+    pinyin_seq: &[&str],
+    dispatch_vec: &[PrioritySet],
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+    unique: bool,
+    edge: &impl Fn(&Item, &str, f64) -> f64,
+) -> (PrioritySet, Vec<(String, f64)>) {
+    let to_idx = pinyin_seq.len() - 1;
+    let mut column = PrioritySet::new(options.beam_width.unwrap_or(path_num));
+    let start_item = Item::new(if use_log_prob { 0.0 } else { 1.0 }, Vec::new(), Vec::new());
+    let mut prefix_phrases = Vec::new();
+
+    for from_idx in options.first_phrase_start(to_idx)..=to_idx {
+        let slice = &pinyin_seq[from_idx..to_idx + 1];
+        // 处理起始位置（from_idx = 0）和后续位置（from_idx >= 1）
+        let prev_items: Vec<&Item> = if from_idx == 0 {
+            vec![&start_item]
+        } else {
+            dispatch_vec[from_idx - 1].iter().collect()
+        };
+        if prev_items.is_empty() {
+            continue;
+        }
+
+        let phrase_prob_pairs = dag.get_phrase(slice, path_num);
+        for prev_item in prev_items {
+            for (phrase, dag_prob) in &phrase_prob_pairs {
+                let log_prob = edge(prev_item, phrase, *dag_prob);
+                let mut word = prev_item.path().clone();
+                word.push(phrase.clone());
+                let mut spans = prev_item.spans().clone();
+                spans.push((from_idx..to_idx + 1, *dag_prob));
 
-            let phrase_prob_pairs = dag.get_phrase(slice, path_num);
-            for (phrase, prob) in phrase_prob_pairs {
-                let word = vec![phrase];
-                let score = if use_log_prob { prob.ln() } else { prob };
-                dispatch_vec[to_idx].put(score, word);
+                let score = if use_log_prob {
+                    prev_item.score() + log_prob
+                } else {
+                    prev_item.score() * log_prob.exp()
+                };
+
+                let item = Item::new(score, word, spans);
+                if unique {
+                    column.put_unique(item);
+                } else {
+                    column.put(item);
+                }
             }
         }
+        if from_idx == 0 {
+            prefix_phrases = phrase_prob_pairs;
+        }
     }
+    (column, prefix_phrases)
+}
 
-    // 处理后续位置（from_idx >= 1）
-    for from_idx in 1..pinyin_num {
-        // 先收集前一个位置的数据，避免借用冲突
-        let prev_items: Vec<_> = dispatch_vec[from_idx - 1]
-            .iter()
-            .map(|item| (item.score(), item.path().clone()))
-            .collect();
+/// 输入法候选窗口使用的结果，包括整句的结果和只覆盖前面几个拼音的词组
+///
+/// 先返回整句的结果，然后按照覆盖的拼音数量从多到少返回从第一个拼音开始的词组，
+/// 相同的汉字只保留第一个。如 nihaoma => [你好吗, 你好, 你, ...]，`Item::spans` 记录了覆盖的拼音范围
+///
+/// # Arguments
+/// * `dag` - DAG 实现
+/// * `pinyin_seq` - 拼音列表
+/// * `path_num` - 整句结果的数量，以及每个前缀的词组数量
+/// * `use_log_prob` - 是否使用对数概率
+pub fn dispatch_prefixes(
+    dag: &impl Dag,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+) -> Vec<Item> {
+    dispatch_prefixes_with_options(
+        dag,
+        pinyin_seq,
+        path_num,
+        use_log_prob,
+        &DecodeOptions::default(),
+    )
+}
 
-        for to_idx in from_idx..pinyin_num {
-            let slice = &pinyin_seq[from_idx..to_idx + 1];
+/// 和 [`dispatch_prefixes`] 相同，options 限制词组长度、每个位置保留的路径数量和时间
+///
+/// 前缀的词组也受词组长度的限制；超过时间限制时整句的结果只覆盖前面的拼音，之后的前缀不再查询
+///
+/// # Arguments
+/// * `dag` - DAG 实现
+/// * `pinyin_seq` - 拼音列表
+/// * `path_num` - 整句结果的数量，以及每个前缀的词组数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `options` - 搜索范围和时间的限制
+pub fn dispatch_prefixes_with_options(
+    dag: &impl Dag,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+) -> Vec<Item> {
+    if pinyin_seq.is_empty() {
+        return Vec::new();
+    }
+    let pinyin_num = pinyin_seq.len();
+    let deadline = options.deadline();
+
+    // 计算整句结果的同时，记录每个前缀的词组，不需要重复查询
+    let mut dispatch_vec = Vec::with_capacity(pinyin_num);
+    let mut prefix_vec = Vec::with_capacity(pinyin_num);
+    for to_idx in 0..pinyin_num {
+        let (column, phrases) = dispatch_step_with_prefix(
+            dag,
+            &pinyin_seq[..to_idx + 1],
+            &dispatch_vec,
+            path_num,
+            use_log_prob,
+            options,
+            false,
+            &|_, _, prob| prob.ln(),
+        );
+        dispatch_vec.push(column);
+        prefix_vec.push(phrases);
+        if DecodeOptions::expired(deadline) {
+            break;
+        }
+    }
+
+    let mut result = dispatch_vec.last().unwrap().to_sorted_vec();
+    for (to_idx, mut phrases) in prefix_vec.into_iter().enumerate().rev() {
+        phrases.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        for (phrase, prob) in phrases {
+            let score = if use_log_prob { prob.ln() } else { prob };
+            result.push(Item::new(score, vec![phrase], vec![(0..to_idx + 1, prob)]));
+        }
+    }
+    dedup_by_text(result)
+}
 
-            let phrase_prob_pairs = dag.get_phrase(slice, path_num);
-            for (prev_score, prev_path) in &prev_items {
-                for (phrase, prob) in &phrase_prob_pairs {
-                    let mut word = prev_path.clone();
+/// 在拼音网格上使用动态规划算法，同时考虑所有的拼音切分方式
+///
+/// 返回的结果中，相同的汉字只保留分数最高的一个，`Item::pinyins` 记录了每个结果使用的拼音切分
+///
+/// # Arguments
+/// * `dag` - DAG 实现
+/// * `lattice` - 拼音网格
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+pub fn dispatch_lattice(
+    dag: &impl Dag,
+    lattice: &PinyinLattice,
+    path_num: usize,
+    use_log_prob: bool,
+) -> Vec<Item> {
+    dispatch_lattice_with_options(
+        dag,
+        lattice,
+        path_num,
+        use_log_prob,
+        &DecodeOptions::default(),
+    )
+}
+
+/// 在拼音网格上使用动态规划算法，options 限制词组长度、每个节点保留的路径数量和时间
+///
+/// 词组长度最多是 [`MAX_LATTICE_PHRASE_LEN`]，超过时间限制时返回已经计算完的最后一个有结果的节点的结果，只覆盖前面的拼音
+///
+/// # Arguments
+/// * `dag` - DAG 实现
+/// * `lattice` - 拼音网格
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `options` - 搜索范围和时间的限制
+pub fn dispatch_lattice_with_options(
+    dag: &impl Dag,
+    lattice: &PinyinLattice,
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+) -> Vec<Item> {
+    dispatch_lattice_with_edge(
+        dag,
+        lattice,
+        path_num,
+        use_log_prob,
+        options,
+        |_, _, prob| prob.ln(),
+    )
+}
+
+/// 和 [`dispatch_lattice_with_options`] 相同，edge 计算一条边的对数分数，见 [`dispatch_with_edge`]
+pub(crate) fn dispatch_lattice_with_edge(
+    dag: &impl Dag,
+    lattice: &PinyinLattice,
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+    edge: impl Fn(&Item, &str, f64) -> f64,
+) -> Vec<Item> {
+    if lattice.is_empty() {
+        return Vec::new();
+    }
+    let node_num = lattice.len();
+    let max_phrase_len = options
+        .max_phrase_len
+        .map_or(MAX_LATTICE_PHRASE_LEN, |len| {
+            len.min(MAX_LATTICE_PHRASE_LEN)
+        });
+    let deadline = options.deadline();
+
+    // 到达每个节点的最优路径，第一个节点是起点
+    let mut dispatch_vec = Vec::with_capacity(node_num);
+    for _ in 0..node_num {
+        dispatch_vec.push(PrioritySet::new(options.beam_width.unwrap_or(path_num)));
+    }
+    let mut end_node = node_num - 1;
+
+    for from_node in 0..node_num - 1 {
+        let prev_items: Vec<_> = if from_node == 0 {
+            vec![Item::new(
+                if use_log_prob { 0.0 } else { 1.0 },
+                Vec::new(),
+                Vec::new(),
+            )]
+        } else {
+            dispatch_vec[from_node].iter().cloned().collect()
+        };
+
+        for (to_node, slice) in lattice.paths_from(from_node, max_phrase_len) {
+            let phrase_prob_pairs = dag.get_phrase(&slice, path_num);
+            for prev_item in &prev_items {
+                let start = prev_item.pinyins().len();
+                for (phrase, dag_prob) in &phrase_prob_pairs {
+                    let log_prob = edge(prev_item, phrase, *dag_prob);
+                    let mut word = prev_item.path().clone();
                     word.push(phrase.clone());
+                    let mut spans = prev_item.spans().clone();
+                    spans.push((start..start + slice.len(), *dag_prob));
+                    let mut pinyins = prev_item.pinyins().clone();
+                    pinyins.extend(slice.iter().map(|pinyin| pinyin.to_string()));
 
                     let score = if use_log_prob {
-                        *prev_score + prob.ln()
+                        prev_item.score() + log_prob
                     } else {
-                        *prev_score * prob
+                        prev_item.score() * log_prob.exp()
                     };
 
-                    dispatch_vec[to_idx].put(score, word);
+                    // 不同的切分可能得到相同的汉字，去重之后再截断，保证返回足够数量的结果
+                    dispatch_vec[to_node]
+                        .put_unique(Item::new(score, word, spans).with_pinyins(pinyins));
                 }
             }
         }
+        // 之后的边都从更后面的节点出发，from_node + 1 之前的节点都已经计算完
+        if DecodeOptions::expired(deadline) {
+            end_node = (1..=from_node + 1)
+                .rev()
+                .find(|node| !dispatch_vec[*node].is_empty())
+                .unwrap_or(from_node + 1);
+            break;
+        }
     }
 
-    // 获取最终结果
-    let mut result: Vec<Item> = dispatch_vec.last().unwrap().to_sorted_vec();
-    result.sort_by(|a, b| {
-        b.score()
-            .partial_cmp(&a.score())
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    let mut result = dispatch_vec[end_node].to_sorted_vec();
+    result.truncate(path_num);
+    result
+}
 
+/// 使用动态规划算法，把简拼转换成汉字
+///
+/// 每个声母会展开成所有以它开头的拼音，按照单字的概率排序之后，
+/// 一段拼音最多尝试 [`MAX_ABBREVIATION_COMBINATIONS`] 种完整拼音的组合，
+/// `Item::pinyins` 记录了每个结果匹配到的完整拼音
+///
+/// # Arguments
+/// * `dag` - DAG 实现
+/// * `pinyin_seq` - 简拼列表，见 [`pinyin_tokenize_abbreviated`](crate::pinyin::pinyin_tokenize_abbreviated)
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+pub fn dispatch_abbreviated(
+    dag: &impl Dag,
+    pinyin_seq: &[AbbreviatedPinyin],
+    path_num: usize,
+    use_log_prob: bool,
+) -> Vec<Item> {
+    dispatch_abbreviated_with_options(
+        dag,
+        pinyin_seq,
+        path_num,
+        use_log_prob,
+        &DecodeOptions::default(),
+    )
+}
+
+/// 使用动态规划算法，把简拼转换成汉字，options 限制词组长度、每个位置保留的路径数量和时间
+///
+/// 词组长度最多是 [`MAX_LATTICE_PHRASE_LEN`]，超过时间限制时返回已经计算完的最后一个有结果的位置的结果，只覆盖前面的简拼
+///
+/// # Arguments
+/// * `dag` - DAG 实现
+/// * `pinyin_seq` - 简拼列表，见 [`pinyin_tokenize_abbreviated`](crate::pinyin::pinyin_tokenize_abbreviated)
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `options` - 搜索范围和时间的限制
+pub fn dispatch_abbreviated_with_options(
+    dag: &impl Dag,
+    pinyin_seq: &[AbbreviatedPinyin],
+    path_num: usize,
+    use_log_prob: bool,
+    options: &DecodeOptions,
+) -> Vec<Item> {
+    if pinyin_seq.is_empty() {
+        return Vec::new();
+    }
+    let pinyin_num = pinyin_seq.len();
+    let max_phrase_len = options
+        .max_phrase_len
+        .map_or(MAX_LATTICE_PHRASE_LEN, |len| {
+            len.min(MAX_LATTICE_PHRASE_LEN)
+        });
+    let deadline = options.deadline();
+    let expanded = pinyin_seq
+        .iter()
+        .map(|pinyin| expand_abbreviated(dag, pinyin))
+        .collect::<Vec<_>>();
+
+    // 到达每个位置的最优路径，第一个位置是起点
+    let mut dispatch_vec = Vec::with_capacity(pinyin_num + 1);
+    for _ in 0..=pinyin_num {
+        dispatch_vec.push(PrioritySet::new(options.beam_width.unwrap_or(path_num)));
+    }
+    let mut end_idx = pinyin_num;
+
+    for from_idx in 0..pinyin_num {
+        let prev_items: Vec<_> = if from_idx == 0 {
+            vec![Item::new(
+                if use_log_prob { 0.0 } else { 1.0 },
+                Vec::new(),
+                Vec::new(),
+            )]
+        } else {
+            dispatch_vec[from_idx].iter().cloned().collect()
+        };
+        if prev_items.is_empty() {
+            continue;
+        }
+
+        let max_to = usize::min(pinyin_num, from_idx + max_phrase_len);
+        for to_idx in from_idx + 1..=max_to {
+            let phrases = match_abbreviated(dag, &expanded[from_idx..to_idx], path_num);
+            for prev_item in &prev_items {
+                for (phrase, prob, slice) in &phrases {
+                    let mut word = prev_item.path().clone();
+                    word.push(phrase.clone());
+                    let mut spans = prev_item.spans().clone();
+                    spans.push((from_idx..to_idx, *prob));
+                    let mut pinyins = prev_item.pinyins().clone();
+                    pinyins.extend(slice.iter().map(|pinyin| pinyin.to_string()));
+
+                    let score = if use_log_prob {
+                        prev_item.score() + prob.ln()
+                    } else {
+                        prev_item.score() * prob
+                    };
+
+                    // 不同的完整拼音可能得到相同的汉字，去重之后再截断
+                    dispatch_vec[to_idx]
+                        .put_unique(Item::new(score, word, spans).with_pinyins(pinyins));
+                }
+            }
+        }
+        // 之后的词组都从更后面的位置开始，from_idx + 1 之前的位置都已经计算完
+        if DecodeOptions::expired(deadline) {
+            end_idx = (1..=from_idx + 1)
+                .rev()
+                .find(|idx| !dispatch_vec[*idx].is_empty())
+                .unwrap_or(from_idx + 1);
+            break;
+        }
+    }
+
+    let mut result = dispatch_vec[end_idx].to_sorted_vec();
+    result.truncate(path_num);
     result
 }
 
+/// 展开一个简拼，按照单字的最大概率降序排列，没有对应单字的拼音排在最后
+fn expand_abbreviated(dag: &impl Dag, pinyin: &AbbreviatedPinyin) -> Vec<(&'static str, f64)> {
+    if let AbbreviatedPinyin::Full(pinyin) = pinyin {
+        return vec![(*pinyin, 1.0)];
+    }
+    let mut expanded = pinyin
+        .expand()
+        .into_iter()
+        .map(|pinyin| {
+            let prob = dag
+                .get_phrase(&[pinyin], 1)
+                .first()
+                .map_or(0.0, |(_, prob)| *prob);
+            (pinyin, prob)
+        })
+        .collect::<Vec<_>>();
+    expanded.sort_by(|a, b| b.1.total_cmp(&a.1));
+    expanded
+}
+
+/// 查询一段简拼可能对应的词组，返回 (词组, 概率, 完整拼音)
+fn match_abbreviated(
+    dag: &impl Dag,
+    options: &[Vec<(&'static str, f64)>],
+    path_num: usize,
+) -> Vec<(String, f64, Vec<&'static str>)> {
+    // 按照单字概率的乘积保留最有可能的组合
+    let mut combinations = vec![(Vec::new(), 1.0)];
+    for option in options {
+        combinations = combinations
+            .iter()
+            .flat_map(|(prefix, weight): &(Vec<&'static str>, f64)| {
+                option.iter().map(move |(pinyin, prob)| {
+                    let mut next = prefix.clone();
+                    next.push(*pinyin);
+                    (next, weight * prob)
+                })
+            })
+            .collect();
+        combinations.sort_by(|a, b| b.1.total_cmp(&a.1));
+        combinations.truncate(MAX_ABBREVIATION_COMBINATIONS);
+    }
+
+    let mut best = HashMap::<String, (f64, Vec<&'static str>)>::new();
+    for (combination, _) in combinations {
+        for (phrase, prob) in dag.get_phrase(&combination, path_num) {
+            if best
+                .get(&phrase)
+                .is_none_or(|(best_prob, _)| prob > *best_prob)
+            {
+                best.insert(phrase, (prob, combination.clone()));
+            }
+        }
+    }
+    let mut phrases = best
+        .into_iter()
+        .map(|(phrase, (prob, pinyins))| (phrase, prob, pinyins))
+        .collect::<Vec<_>>();
+    phrases.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    phrases.truncate(path_num);
+    phrases
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::collections::HashMap;
 
+    /// shi 和 zhang 两个拼音的单字，单独转换时是 "是张"，混合解码和语言模型解码的测试也使用它
+    pub(crate) struct ShiZhangDag;
+
+    impl Dag for ShiZhangDag {
+        fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+            let phrases = match pinyin_list.join(",").as_str() {
+                "shi" => vec![("是".to_owned(), 0.6), ("市".to_owned(), 0.4)],
+                "zhang" => vec![("张".to_owned(), 0.6), ("长".to_owned(), 0.4)],
+                _ => Vec::new(),
+            };
+            phrases.into_iter().take(num).collect()
+        }
+    }
+
     // 为测试创建一个简单的DAG参数实现
     struct TestDag {
         data: HashMap<String, Vec<(String, f64)>>,
@@ -124,5 +651,286 @@ mod tests {
         let pinyin_list = vec!["ni", "hao"];
         let result = dispatch(&params, &pinyin_list, 2, false);
         assert!(!result.is_empty());
+        assert_eq!(result[0].path(), &vec!["你好".to_string()]);
+        assert_eq!(result[0].spans(), &vec![(0..2, 0.9)]);
+        assert_eq!(result[1].spans(), &vec![(0..1, 0.8), (1..2, 0.7)]);
+    }
+
+    #[test]
+    fn test_dag_lattice() {
+        let mut params = TestDag::new();
+        params
+            .data
+            .insert("xi,an".to_string(), vec![("西安".to_string(), 0.9)]);
+        params
+            .data
+            .insert("xian".to_string(), vec![("先".to_string(), 0.5)]);
+        let lattice = crate::pinyin::pinyin_lattice("xian");
+        let result = dispatch_lattice(&params, &lattice, 3, false);
+        assert_eq!(result[0].path(), &vec!["西安".to_string()]);
+        assert_eq!(
+            result[0].pinyins(),
+            &vec!["xi".to_string(), "an".to_string()]
+        );
+        assert_eq!(result[0].spans(), &vec![(0..2, 0.9)]);
+        assert_eq!(result[1].path(), &vec!["先".to_string()]);
+
+        // 词组最多一个拼音时不能使用 "西安"
+        let options = DecodeOptions {
+            max_phrase_len: Some(1),
+            ..Default::default()
+        };
+        let result = dispatch_lattice_with_options(&params, &lattice, 3, false, &options);
+        assert!(
+            result
+                .iter()
+                .all(|item| item.path().len() == item.pinyins().len())
+        );
+
+        // 超时之后返回已经计算完的部分结果
+        let options = DecodeOptions {
+            time_limit: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        let lattice = crate::pinyin::pinyin_lattice("nihao");
+        let result = dispatch_lattice_with_options(&params, &lattice, 3, false, &options);
+        assert_eq!(result[0].path(), &vec!["你".to_string()]);
+        assert_eq!(result[0].pinyins(), &vec!["ni".to_string()]);
+    }
+
+    #[test]
+    fn test_dag_lattice_dedup() {
+        // xi,an 和 xian 都可以得到西安，去重之后仍然返回两个结果
+        let mut params = TestDag::new();
+        params
+            .data
+            .insert("xi,an".to_string(), vec![("西安".to_string(), 0.9)]);
+        params.data.insert(
+            "xian".to_string(),
+            vec![("西安".to_string(), 0.5), ("先".to_string(), 0.4)],
+        );
+        let lattice = crate::pinyin::pinyin_lattice("xian");
+        let result = dispatch_lattice(&params, &lattice, 2, false);
+        let texts = result
+            .iter()
+            .map(|item| item.path().concat())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["西安", "先"]);
+        assert_eq!(result[0].score(), 0.9);
+    }
+
+    #[test]
+    fn test_dag_prefixes() {
+        let mut params = TestDag::new();
+        params
+            .data
+            .insert("ma".to_string(), vec![("吗".to_string(), 0.6)]);
+        params
+            .data
+            .insert("ni,hao,ma".to_string(), vec![("你好吗".to_string(), 0.95)]);
+        let result = dispatch_prefixes(&params, &["ni", "hao", "ma"], 2, false);
+        let texts = result
+            .iter()
+            .map(|item| item.path().concat())
+            .collect::<Vec<_>>();
+        // 整句的结果在前，然后是覆盖前两个和前一个拼音的词组
+        assert_eq!(texts, ["你好吗", "你好", "你", "泥"]);
+        assert_eq!(result[1].spans(), &vec![(0..2, 0.9)]);
+        assert_eq!(result[3].spans(), &vec![(0..1, 0.2)]);
+        assert!(dispatch_prefixes(&params, &[], 2, false).is_empty());
+
+        // 词组长度的限制也作用于前缀的词组
+        let options = DecodeOptions {
+            max_phrase_len: Some(1),
+            ..Default::default()
+        };
+        let result =
+            dispatch_prefixes_with_options(&params, &["ni", "hao", "ma"], 2, false, &options);
+        assert_eq!(result[0].path().len(), 3);
+        assert!(
+            result
+                .iter()
+                .all(|item| item.spans().len() == item.path().len())
+        );
+        assert!(result.iter().all(|item| item.path().concat() != "你好"));
+        // 超时之后整句的结果只覆盖前面的拼音
+        let options = DecodeOptions {
+            time_limit: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        let result =
+            dispatch_prefixes_with_options(&params, &["ni", "hao", "ma"], 2, false, &options);
+        assert_eq!(result[0].spans(), &vec![(0..1, 0.8)]);
+    }
+
+    #[test]
+    fn test_fuzzy_dag() {
+        use crate::pinyin::{FuzzyRule, FuzzyRules};
+
+        let rules = FuzzyRules::new().with_rule(FuzzyRule::NL);
+        let fuzzy = FuzzyDag::new(TestDag::new(), rules);
+        // li 展开成 ni，概率乘以惩罚系数
+        assert_eq!(
+            fuzzy.get_phrase(&["li"], 2),
+            [("你".to_string(), 0.4), ("泥".to_string(), 0.1)]
+        );
+        let result = dispatch(&fuzzy, &["li", "hao"], 2, false);
+        assert_eq!(result[0].path(), &vec!["你好".to_string()]);
+        assert_eq!(result[0].spans(), &vec![(0..2, 0.45)]);
+        // 原来的拼音不受影响
+        assert_eq!(
+            fuzzy.get_phrase(&["ni", "hao"], 2),
+            [("你好".to_string(), 0.9)]
+        );
+    }
+
+    #[test]
+    fn test_dag_abbreviated() {
+        use crate::pinyin::pinyin_tokenize_abbreviated;
+
+        let mut params = TestDag::new();
+        params
+            .data
+            .insert("bei".to_string(), vec![("北".to_string(), 0.6)]);
+        params
+            .data
+            .insert("jing".to_string(), vec![("京".to_string(), 0.5)]);
+        params
+            .data
+            .insert("bei,jing".to_string(), vec![("北京".to_string(), 0.9)]);
+        let result = dispatch_abbreviated(&params, &pinyin_tokenize_abbreviated("bj"), 3, false);
+        assert_eq!(result[0].path(), &vec!["北京".to_string()]);
+        assert_eq!(
+            result[0].pinyins(),
+            &vec!["bei".to_string(), "jing".to_string()]
+        );
+        assert_eq!(result[0].spans(), &vec![(0..2, 0.9)]);
+        // 北 京 和北京的文本相同，只保留分数最高的一个
+        assert_eq!(result.len(), 1);
+        // 去重之后再截断，返回的结果数量不会因为重复的文本而减少
+        params.data.insert(
+            "bei".to_string(),
+            vec![("北".to_string(), 0.6), ("被".to_string(), 0.4)],
+        );
+        let result = dispatch_abbreviated(&params, &pinyin_tokenize_abbreviated("bj"), 2, false);
+        let texts = result
+            .iter()
+            .map(|item| item.path().concat())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["北京", "被京"]);
+        // 完整拼音和声母混合输入
+        let result = dispatch_abbreviated(&params, &pinyin_tokenize_abbreviated("beij"), 3, false);
+        assert_eq!(result[0].path(), &vec!["北京".to_string()]);
+        let result = dispatch_abbreviated(&params, &pinyin_tokenize_abbreviated("nih"), 3, false);
+        assert_eq!(result[0].path(), &vec!["你好".to_string()]);
+        assert!(dispatch_abbreviated(&params, &[], 3, false).is_empty());
+
+        let bj = pinyin_tokenize_abbreviated("bj");
+        let options = DecodeOptions {
+            max_phrase_len: Some(1),
+            ..Default::default()
+        };
+        let result = dispatch_abbreviated_with_options(&params, &bj, 3, false, &options);
+        assert_eq!(result[0].path(), &vec!["北".to_string(), "京".to_string()]);
+        // 超时之后返回已经计算完的部分结果
+        let options = DecodeOptions {
+            time_limit: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        let result = dispatch_abbreviated_with_options(&params, &bj, 3, false, &options);
+        assert_eq!(result[0].path(), &vec!["北".to_string()]);
+        assert_eq!(result[0].pinyins(), &vec!["bei".to_string()]);
+    }
+
+    #[test]
+    fn test_toned_dag() {
+        use crate::pinyin::ToneTable;
+
+        let table =
+            ToneTable::from_reader("你 ni3\n泥 ni2\n好 hao3\n号 hao4\n".as_bytes()).unwrap();
+        let toned = TonedDag::new(TestDag::new(), table.clone());
+        let phrases = toned.get_phrase(&["ni2"], 2);
+        assert_eq!(phrases[0].0, "泥");
+        assert!((phrases[1].1 - 0.08).abs() < 1e-9);
+        // 没有声调时和内部的 DAG 一致
+        assert_eq!(toned.get_phrase(&["ni"], 2)[0].0, "你");
+        // 惩罚系数为 0 时过滤掉声调不一致的词组
+        let toned = TonedDag::new(TestDag::new(), table).with_penalty(0.0);
+        assert_eq!(toned.get_phrase(&["hao4"], 2), [("号".to_string(), 0.3)]);
+        let result = dispatch(&toned, &["ni3", "hao4"], 2, false);
+        assert_eq!(result[0].path(), &vec!["你".to_string(), "号".to_string()]);
+    }
+
+    #[test]
+    fn test_user_dict() {
+        let text = "# 用户词典\n尼好 ni hao 3\n泥 ni 1\n";
+        let dict = UserDict::from_reader(TestDag::new(), text.as_bytes()).unwrap();
+        assert_eq!(dict.len(), 2);
+        // 用户词语的概率乘以权重，和模型中相同的词组取较大的概率
+        assert_eq!(
+            dict.get_phrase(&["ni"], 2),
+            [("泥".to_string(), 1.0), ("你".to_string(), 0.8)]
+        );
+        let result = dispatch(&dict, &["ni", "hao"], 2, false);
+        assert_eq!(result[0].path(), &vec!["尼好".to_string()]);
+
+        let mut dict = dict.with_weight(0.5);
+        let result = dispatch(&dict, &["ni", "hao"], 2, false);
+        assert_eq!(result[0].path(), &vec!["你好".to_string()]);
+
+        assert!(dict.add("尼好", &["ni", "hao"], 1.0));
+        assert!(!dict.add("尼好", &["ni"], 1.0));
+        for freq in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(!dict.add("尼好", &["ni", "hao"], freq));
+        }
+        assert!(dict.remove("泥", &["ni"]));
+        let mut saved = Vec::new();
+        dict.write_to(&mut saved).unwrap();
+        assert_eq!(String::from_utf8(saved).unwrap(), "尼好 ni hao 4\n");
+
+        // 同一个拼音下的用户词语按照词频保持比例，最大的概率为 1
+        let dict = UserDict::from_reader(TestDag::new(), "拟 ni 3\n泥 ni 2\n".as_bytes()).unwrap();
+        assert_eq!(
+            dict.get_phrase(&["ni"], 3),
+            [
+                ("拟".to_string(), 1.0),
+                ("你".to_string(), 0.8),
+                ("泥".to_string(), 2.0 / 3.0),
+            ]
+        );
+        // 大写的拼音和添加时的拼音相同
+        assert_eq!(dict.get_phrase(&["NI"], 1), [("拟".to_string(), 1.0)]);
+
+        let Err(err) = UserDict::from_reader(TestDag::new(), "你好 ni 1\n".as_bytes()) else {
+            panic!("the line should be invalid");
+        };
+        assert_eq!(err.to_string(), "Line 1 is invalid: 2 hanzi but 1 pinyin");
+        let Err(err) = UserDict::from_reader(TestDag::new(), "你 ni inf\n".as_bytes()) else {
+            panic!("the line should be invalid");
+        };
+        assert_eq!(err.to_string(), "Line 1 is invalid: invalid frequency inf");
+    }
+
+    #[test]
+    fn test_learned_dag() {
+        use crate::learning::LearningHistory;
+
+        let history = LearningHistory::new().into_shared();
+        let learned = LearnedDag::new(TestDag::new(), history.clone());
+        assert_eq!(learned.get_phrase(&["ni"], 1)[0].0, "你");
+        // 选择过的词组排在前面，模型中没有的词组也会成为候选结果
+        assert!(learned.record(&["ni"], "泥"));
+        assert!(learned.record(&["ni", "hao"], "拟好"));
+        assert_eq!(learned.get_phrase(&["ni"], 1)[0].0, "泥");
+        assert!(
+            learned
+                .get_phrase(&["ni", "hao"], 2)
+                .iter()
+                .any(|(phrase, _)| phrase == "拟好")
+        );
+        // 选择一次之后整句的结果也会变成选择过的词组
+        let result = dispatch(&learned, &["ni", "hao"], 1, false);
+        assert_eq!(result[0].path(), &vec!["拟好".to_string()]);
+        assert!(!history.read().unwrap().is_empty());
     }
 }
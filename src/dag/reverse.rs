@@ -0,0 +1,210 @@
+use crate::pinyin::{PinyinStyle, ToneTable, TonedPinyin};
+use pinyinchch_type::dag::DagEntries;
+use std::collections::HashMap;
+
+/// 汉字转拼音，使用 DAG 模型中的词条作为反向索引
+///
+/// 先按照词组切分文本，切分时优先使用更长的词组，词组数量相同时选择概率更大的，
+/// 所以多音字的读音由它所在的词组决定，如 银行 => yin hang，行走 => xing zou
+#[derive(Debug, Clone, Default)]
+pub struct HanziToPinyin {
+    /// 词组（包括单字）和它概率最大的读音
+    readings: HashMap<String, (Vec<String>, f64)>,
+    /// 最长的词组包含的字数
+    max_len: usize,
+    tones: Option<ToneTable>,
+}
+
+impl HanziToPinyin {
+    /// 遍历 DAG 模型中的所有词条，构建反向索引
+    pub fn new(dag: &impl DagEntries) -> Self {
+        let mut readings = HashMap::<String, (Vec<String>, f64)>::new();
+        let mut max_len = 0;
+        dag.for_each_entry(&mut |pinyin_list, phrase, prob| {
+            let len = phrase.chars().count();
+            if len != pinyin_list.len() {
+                return;
+            }
+            if readings
+                .get(phrase)
+                .is_none_or(|(_, best_prob)| prob > *best_prob)
+            {
+                let pinyins = pinyin_list
+                    .iter()
+                    .map(|pinyin| pinyin.to_string())
+                    .collect();
+                readings.insert(phrase.to_owned(), (pinyins, prob));
+                max_len = usize::max(max_len, len);
+            }
+        });
+        Self {
+            readings,
+            max_len,
+            tones: None,
+        }
+    }
+
+    /// 设置声调表，[`PinyinStyle::ToneMarks`] 和 [`PinyinStyle::ToneNumbers`] 需要声调表才能输出声调
+    pub fn with_tone_table(mut self, table: ToneTable) -> Self {
+        self.tones = Some(table);
+        self
+    }
+
+    /// 把文本转换成拼音，每个字对应一个结果，没有读音的字符（标点、字母等）原样输出
+    pub fn convert(&self, text: impl AsRef<str>, style: PinyinStyle) -> Vec<String> {
+        self.convert_toned(text)
+            .into_iter()
+            .map(|reading| match reading {
+                Ok(pinyin) => pinyin.format(style),
+                Err(ch) => ch.to_string(),
+            })
+            .collect()
+    }
+
+    /// 把文本转换成带声调的拼音，没有读音的字符返回 Err(字符)，没有声调表时声调为 None
+    pub fn convert_toned(&self, text: impl AsRef<str>) -> Vec<Result<TonedPinyin, char>> {
+        let chars = text.as_ref().chars().collect::<Vec<_>>();
+        let num = chars.len();
+        // best[i] 表示前 i 个字的最优切分，(词组数量, 对数概率之和, 上一个切分位置)
+        let mut best = vec![(usize::MAX, f64::NEG_INFINITY, 0); num + 1];
+        best[0] = (0, 0.0, 0);
+        for start in 0..num {
+            let (count, log_prob, _) = best[start];
+            if count == usize::MAX {
+                continue;
+            }
+            for end in start + 1..=usize::min(num, start + self.max_len.max(1)) {
+                let phrase = chars[start..end].iter().collect::<String>();
+                let prob = match self.readings.get(&phrase) {
+                    Some((_, prob)) => prob.ln(),
+                    // 没有读音的字符单独作为一段
+                    None if end == start + 1 => 0.0,
+                    None => continue,
+                };
+                let candidate = (count + 1, log_prob + prob, start);
+                if candidate.0 < best[end].0
+                    || candidate.0 == best[end].0 && candidate.1 > best[end].1
+                {
+                    best[end] = candidate;
+                }
+            }
+        }
+
+        let mut segments = Vec::new();
+        let mut end = num;
+        while end > 0 {
+            let start = best[end].2;
+            segments.push(start..end);
+            end = start;
+        }
+        segments
+            .into_iter()
+            .rev()
+            .flat_map(|range| {
+                let phrase = chars[range.clone()].iter().collect::<String>();
+                match self.readings.get(&phrase) {
+                    Some((pinyins, _)) => self
+                        .with_tones(&phrase, pinyins)
+                        .into_iter()
+                        .map(Ok)
+                        .collect::<Vec<_>>(),
+                    None => chars[range].iter().map(|ch| Err(*ch)).collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// 从声调表中查找词组的声调，词组的读音优先于单字的读音
+    fn with_tones(&self, phrase: &str, pinyins: &[String]) -> Vec<TonedPinyin> {
+        let Some(table) = &self.tones else {
+            return pinyins
+                .iter()
+                .map(|pinyin| TonedPinyin::new(pinyin.as_str(), None))
+                .collect();
+        };
+        if let Some(readings) = table.phrase_readings(phrase)
+            && readings
+                .iter()
+                .map(|reading| &reading.pinyin)
+                .eq(pinyins.iter())
+        {
+            return readings.to_vec();
+        }
+        phrase
+            .chars()
+            .zip(pinyins)
+            .map(|(ch, pinyin)| {
+                let tone = table.char_tones(ch, pinyin).first().copied();
+                TonedPinyin::new(pinyin.as_str(), tone)
+            })
+            .collect()
+    }
+}
+
+/// 把文本转换成拼音，每个字对应一个结果，见 [`HanziToPinyin`]
+///
+/// 每次调用都会重新构建反向索引，多次转换时应该复用 [`HanziToPinyin`]
+pub fn hanzi_to_pinyin(
+    dag: &impl DagEntries,
+    text: impl AsRef<str>,
+    style: PinyinStyle,
+) -> Vec<String> {
+    HanziToPinyin::new(dag).convert(text, style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HanziToPinyin;
+    use crate::pinyin::{PinyinStyle, ToneTable};
+    use pinyinchch_type::dag::DagEntries;
+
+    struct TestEntries(Vec<(&'static str, &'static str, f64)>);
+
+    impl DagEntries for TestEntries {
+        fn for_each_entry(&self, f: &mut dyn FnMut(&[&str], &str, f64)) {
+            for (pinyin_key, phrase, prob) in &self.0 {
+                let pinyin_list = pinyin_key.split(',').collect::<Vec<_>>();
+                f(&pinyin_list, phrase, *prob);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hanzi_to_pinyin() {
+        let entries = TestEntries(vec![
+            ("xing", "行", 0.4),
+            ("hang", "行", 0.1),
+            ("yin", "银", 0.5),
+            ("zou", "走", 0.5),
+            ("yin,hang", "银行", 0.8),
+            ("zhang", "长", 0.3),
+            ("chang", "长", 0.2),
+            ("chang,jiang", "长江", 0.9),
+            ("jiang", "江", 0.6),
+        ]);
+        let converter = HanziToPinyin::new(&entries);
+        let plain = |text| converter.convert(text, PinyinStyle::Plain);
+        assert_eq!(plain("银行"), ["yin", "hang"]);
+        assert_eq!(plain("行走"), ["xing", "zou"]);
+        assert_eq!(plain("长江"), ["chang", "jiang"]);
+        assert_eq!(plain("长"), ["zhang"]);
+        assert_eq!(plain("走，行"), ["zou", "，", "xing"]);
+        assert_eq!(
+            converter.convert("长江", PinyinStyle::Initials),
+            ["ch", "j"]
+        );
+
+        let table =
+            ToneTable::from_reader("行 xing2 hang2\n银 yin2\n长 zhang3 chang2\n".as_bytes())
+                .unwrap();
+        let converter = converter.with_tone_table(table);
+        assert_eq!(
+            converter.convert("银行长江", PinyinStyle::ToneMarks),
+            ["yín", "háng", "cháng", "jiang"]
+        );
+        assert_eq!(
+            converter.convert("银行", PinyinStyle::ToneNumbers),
+            ["yin2", "hang2"]
+        );
+    }
+}
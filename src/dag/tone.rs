@@ -0,0 +1,70 @@
+use crate::pinyin::{DEFAULT_TONE_PENALTY, ToneTable, TonedPinyin};
+use pinyinchch_type::dag::Dag;
+
+/// 支持声调的 DAG，拼音列表中的拼音可以带有声调，如 ["bei3", "jing1"]
+///
+/// 去除声调之后查询内部的 DAG，词组中每有一个字的声调和输入不一致，概率就乘以一次惩罚系数，
+/// 惩罚系数为 0 时，这些词组会被过滤掉
+#[derive(Debug, Clone)]
+pub struct TonedDag<D> {
+    dag: D,
+    table: ToneTable,
+    penalty: f64,
+}
+
+impl<D: Dag> TonedDag<D> {
+    pub fn new(dag: D, table: ToneTable) -> Self {
+        Self {
+            dag,
+            table,
+            penalty: DEFAULT_TONE_PENALTY,
+        }
+    }
+
+    /// 设置声调不一致时的惩罚系数，取值范围是 [0, 1]
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.dag
+    }
+
+    pub fn table(&self) -> &ToneTable {
+        &self.table
+    }
+}
+
+impl<D: Dag> Dag for TonedDag<D> {
+    fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+        let observations = pinyin_list
+            .iter()
+            .map(TonedPinyin::parse)
+            .collect::<Vec<_>>();
+        let plain = observations
+            .iter()
+            .map(|observation| observation.pinyin.as_str())
+            .collect::<Vec<_>>();
+        if observations
+            .iter()
+            .all(|observation| observation.tone.is_none())
+        {
+            return self.dag.get_phrase(&plain, num);
+        }
+        // 多取一些候选，避免声调一致的词组被截断
+        let mut phrases = self
+            .dag
+            .get_phrase(&plain, num.saturating_mul(2))
+            .into_iter()
+            .filter_map(|(phrase, prob)| {
+                let mismatches = self.table.mismatches(&phrase, &observations);
+                let prob = prob * self.penalty.powi(mismatches as i32);
+                (mismatches == 0 || prob > 0.0).then_some((phrase, prob))
+            })
+            .collect::<Vec<_>>();
+        phrases.sort_by(|a, b| b.1.total_cmp(&a.1));
+        phrases.truncate(num);
+        phrases
+    }
+}
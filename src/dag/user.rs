@@ -0,0 +1,208 @@
+use crate::error::{DataError, InvalidLineSnafu, ReadFileSnafu, ReadLineSnafu, WriteFileSnafu};
+use pinyinchch_type::dag::{Dag, DagEntries};
+use snafu::{OptionExt, ResultExt, ensure};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// 用户词语的概率默认乘以的权重
+pub const DEFAULT_USER_WEIGHT: f64 = 2.0;
+
+/// 用户词典，在另一个 DAG 模型之上添加公司、产品、人名等模型中没有的词语
+///
+/// 词典的文本格式是每行一个词语：`词语 pin yin 词频`，以 `#` 开头的行是注释。
+/// 同一个拼音下用户词语的概率按照词频归一化，再乘以权重，乘以权重之后超过 1 时按比例缩小，
+/// 概率最大的词语为 1，词语之间的比例保持不变。和内部模型中的同一个词组取概率较大的一个
+#[derive(Debug, Clone)]
+pub struct UserDict<D> {
+    dag: D,
+    /// 以逗号连接的拼音和这个拼音下的 (词语, 词频)
+    entries: HashMap<String, Vec<(String, f64)>>,
+    weight: f64,
+}
+
+impl<D: Dag> UserDict<D> {
+    pub fn new(dag: D) -> Self {
+        Self {
+            dag,
+            entries: HashMap::new(),
+            weight: DEFAULT_USER_WEIGHT,
+        }
+    }
+
+    pub fn from_path(dag: D, path: impl AsRef<Path>) -> Result<Self, DataError> {
+        let path = path.as_ref();
+        let file = File::open(path).context(ReadFileSnafu { path })?;
+        Self::from_reader(dag, BufReader::new(file))
+    }
+
+    pub fn from_reader(dag: D, reader: impl BufRead) -> Result<Self, DataError> {
+        let mut dict = Self::new(dag);
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line.context(ReadLineSnafu { line: idx + 1 })?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            ensure!(
+                fields.len() >= 3,
+                InvalidLineSnafu {
+                    line: idx + 1,
+                    reason: "expected phrase, pinyin and frequency"
+                }
+            );
+            let (phrase, rest) = fields.split_first().unwrap();
+            let (freq, pinyins) = rest.split_last().unwrap();
+            let freq = freq
+                .parse::<f64>()
+                .ok()
+                .filter(|freq| valid_freq(*freq))
+                .context(InvalidLineSnafu {
+                    line: idx + 1,
+                    reason: format!("invalid frequency {freq}"),
+                })?;
+            let len = phrase.chars().count();
+            ensure!(
+                dict.add(phrase, pinyins, freq),
+                InvalidLineSnafu {
+                    line: idx + 1,
+                    reason: format!("{len} hanzi but {} pinyin", pinyins.len())
+                }
+            );
+        }
+        Ok(dict)
+    }
+
+    /// 设置用户词语的权重，权重越大，用户词语越容易排在模型的词组之前
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// 添加词语，已经存在时累加词频，词语的字数和拼音数量不一致或者词频不是有限的正数时返回 false
+    pub fn add(&mut self, phrase: &str, pinyins: &[&str], freq: f64) -> bool {
+        if !valid_freq(freq) || pinyins.is_empty() || phrase.chars().count() != pinyins.len() {
+            return false;
+        }
+        let phrases = self.entries.entry(pinyin_key(pinyins)).or_default();
+        match phrases.iter_mut().find(|(hanzi, _)| hanzi == phrase) {
+            Some((_, total)) => *total += freq,
+            None => phrases.push((phrase.to_owned(), freq)),
+        }
+        true
+    }
+
+    /// 删除词语，返回是否删除成功
+    pub fn remove(&mut self, phrase: &str, pinyins: &[&str]) -> bool {
+        let key = pinyin_key(pinyins);
+        let Some(phrases) = self.entries.get_mut(&key) else {
+            return false;
+        };
+        let len = phrases.len();
+        phrases.retain(|(hanzi, _)| hanzi != phrase);
+        let removed = phrases.len() != len;
+        if phrases.is_empty() {
+            self.entries.remove(&key);
+        }
+        removed
+    }
+
+    /// 用户词语的数量
+    pub fn len(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.dag
+    }
+
+    /// 把所有的用户词语保存到文件，格式和 [`UserDict::from_path`] 一致
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DataError> {
+        let path = path.as_ref();
+        let file = File::create(path).context(WriteFileSnafu { path })?;
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer)
+            .and_then(|_| writer.flush())
+            .context(WriteFileSnafu { path })
+    }
+
+    /// 按照拼音的顺序写出所有的用户词语
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut keys = self.entries.keys().collect::<Vec<_>>();
+        keys.sort_unstable();
+        for key in keys {
+            for (phrase, freq) in &self.entries[key] {
+                writeln!(writer, "{phrase} {} {freq}", key.replace(',', " "))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 某个拼音下用户词语的概率
+    fn user_phrases(&self, key: &str) -> Vec<(String, f64)> {
+        let Some(phrases) = self.entries.get(key) else {
+            return Vec::new();
+        };
+        let sum = phrases.iter().map(|(_, freq)| freq).sum::<f64>();
+        let max = phrases.iter().map(|(_, freq)| *freq).fold(0.0, f64::max);
+        // 整体缩放，保证最大的概率不超过 1，同时保持词语之间的比例
+        let scale = f64::min(self.weight / sum, 1.0 / max);
+        phrases
+            .iter()
+            .map(|(phrase, freq)| (phrase.clone(), freq * scale))
+            .collect()
+    }
+}
+
+/// 以逗号连接的小写拼音
+fn pinyin_key(pinyins: &[&str]) -> String {
+    pinyins
+        .iter()
+        .map(|pinyin| pinyin.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 词频必须是有限的正数，NaN 和无穷大会让同一个拼音下所有词语的概率失效
+fn valid_freq(freq: f64) -> bool {
+    freq > 0.0 && freq.is_finite()
+}
+
+impl<D: Dag> Dag for UserDict<D> {
+    fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+        let user_phrases = self.user_phrases(&pinyin_key(pinyin_list));
+        if user_phrases.is_empty() {
+            return self.dag.get_phrase(pinyin_list, num);
+        }
+        // 多取一些候选，避免和用户词语相同的词组占用名额
+        let mut phrases = self.dag.get_phrase(pinyin_list, num + user_phrases.len());
+        for (phrase, prob) in user_phrases {
+            match phrases.iter_mut().find(|(hanzi, _)| *hanzi == phrase) {
+                Some((_, best)) => *best = f64::max(*best, prob),
+                None => phrases.push((phrase, prob)),
+            }
+        }
+        phrases.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        phrases.truncate(num);
+        phrases
+    }
+}
+
+impl<D: Dag + DagEntries> DagEntries for UserDict<D> {
+    fn for_each_entry(&self, f: &mut dyn FnMut(&[&str], &str, f64)) {
+        self.dag.for_each_entry(f);
+        for key in self.entries.keys() {
+            let pinyin_list = key.split(',').collect::<Vec<_>>();
+            for (phrase, prob) in self.user_phrases(key) {
+                f(&pinyin_list, &phrase, prob);
+            }
+        }
+    }
+}
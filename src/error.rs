@@ -0,0 +1,22 @@
+use snafu::Snafu;
+use std::path::PathBuf;
+
+/// 加载词典等文本数据时可能出现的错误
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum DataError {
+    #[snafu(display("Couldn't read file {}", path.display()))]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Couldn't write file {}", path.display()))]
+    WriteFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Couldn't read line {line}"))]
+    ReadLine { line: usize, source: std::io::Error },
+    #[snafu(display("Line {line} is invalid: {reason}"))]
+    InvalidLine { line: usize, reason: String },
+}
@@ -0,0 +1,55 @@
+use crate::pinyin::FuzzyRules;
+use pinyinchch_type::hmm::Hmm;
+
+/// 支持模糊音的 HMM，观测值（拼音）会先按照模糊音规则展开，再查询内部的 HMM
+///
+/// 发射概率取所有模糊音中最大的一个，模糊音的发射概率会乘以替换的惩罚系数
+#[derive(Debug, Clone)]
+pub struct FuzzyHmm<H> {
+    hmm: H,
+    rules: FuzzyRules,
+}
+
+impl<H: Hmm> FuzzyHmm<H> {
+    pub fn new(hmm: H, rules: FuzzyRules) -> Self {
+        Self { hmm, rules }
+    }
+
+    pub fn inner(&self) -> &H {
+        &self.hmm
+    }
+
+    pub fn rules(&self) -> &FuzzyRules {
+        &self.rules
+    }
+}
+
+impl<H: Hmm> Hmm for FuzzyHmm<H> {
+    fn start(&self, state: &str) -> f64 {
+        self.hmm.start(state)
+    }
+
+    fn emission(&self, state: &str, observation: &str) -> f64 {
+        self.rules
+            .expand(observation)
+            .into_iter()
+            .map(|(pinyin, weight)| self.hmm.emission(state, &pinyin) * weight)
+            .fold(0.0, f64::max)
+    }
+
+    fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+        self.hmm.transition(from_state, to_state)
+    }
+
+    fn get_states(&self, observation: &str) -> Vec<String> {
+        let mut states = Vec::<String>::new();
+        for (pinyin, _) in self.rules.expand(observation) {
+            for state in self.hmm.get_states(&pinyin) {
+                if !states.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+        states
+    }
+}
@@ -0,0 +1,193 @@
+use crate::hmm::{ViterbiColumn, viterbi_result};
+use crate::priority::Item;
+use pinyinchch_type::hmm::{Hmm, IndexedHmm, ObsId, StateId};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
+/// 使用整数编号的 Viterbi 算法，结果和 [`viterbi`](crate::hmm::viterbi) 一致
+///
+/// * `hmm` - 使用整数编号的 HMM 实现，字符串版本的模型可以用 [`InternedHmm`] 包装
+/// * `pinyin_seq` - 需要转换的拼音序列
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `min_prob` - 最小概率值，防止概率为0
+pub fn viterbi_indexed(
+    hmm: &impl IndexedHmm,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+    min_prob: f64,
+) -> Vec<Item> {
+    viterbi_indexed_with_context(hmm, None, pinyin_seq, path_num, use_log_prob, min_prob)
+}
+
+/// 使用整数编号的 Viterbi 算法，context 是已经确定的前一个汉字，结果和 [`viterbi_with_context`](crate::hmm::viterbi_with_context) 一致
+///
+/// 第一个拼音使用 [`IndexedHmm::transition_from_name`] 代替起始概率，模型中没有 context 这个汉字时转移概率是 min_prob
+pub fn viterbi_indexed_with_context(
+    hmm: &impl IndexedHmm,
+    context: Option<&str>,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+    min_prob: f64,
+) -> Vec<Item> {
+    if pinyin_seq.is_empty() {
+        return Vec::new();
+    }
+
+    let mut columns: Vec<ViterbiColumn<StateId>> = Vec::with_capacity(pinyin_seq.len());
+    for cur_obs in pinyin_seq {
+        // 模型中没有的拼音不对应任何汉字，不会有结果
+        let Some(obs) = hmm.observation_id(cur_obs) else {
+            return Vec::new();
+        };
+        let states = hmm.states(obs).into_owned();
+        let emission = |state: &StateId| f64::max(hmm.emission(*state, obs), min_prob);
+        let column = match (columns.last(), context) {
+            // 从 context 出发和从只有一个状态的列转移的结果相同
+            (None, Some(context)) => ViterbiColumn::first(
+                states,
+                |state| {
+                    let transition = hmm.transition_from_name(context, *state);
+                    (f64::max(transition, min_prob), emission(state))
+                },
+                use_log_prob,
+            ),
+            (None, None) => ViterbiColumn::first(
+                states,
+                |state| (f64::max(hmm.start(*state), min_prob), emission(state)),
+                use_log_prob,
+            ),
+            (Some(prev), _) => prev.next(
+                states,
+                emission,
+                |from, to| f64::max(hmm.transition(*from, *to), min_prob),
+                path_num,
+                use_log_prob,
+            ),
+        };
+        columns.push(column);
+    }
+
+    viterbi_result(&columns, path_num, |state| {
+        hmm.state_name(*state).into_owned()
+    })
+}
+
+/// 字符串和编号之间的映射
+#[derive(Debug, Default)]
+struct Interner {
+    states: Vec<String>,
+    state_ids: HashMap<String, StateId>,
+    observations: Vec<String>,
+    obs_ids: HashMap<String, ObsId>,
+    obs_states: Vec<Vec<StateId>>,
+}
+
+impl Interner {
+    fn intern_state(&mut self, state: &str) -> StateId {
+        if let Some(id) = self.state_ids.get(state) {
+            return *id;
+        }
+        let id = StateId(self.states.len() as u32);
+        self.states.push(state.to_owned());
+        self.state_ids.insert(state.to_owned(), id);
+        id
+    }
+}
+
+/// 把字符串版本的 [`Hmm`] 包装成 [`IndexedHmm`]
+///
+/// 第一次查询一个拼音时为它和它对应的汉字分配编号，之后查询概率时通过编号找到字符串，再调用被包装的模型。
+/// 编号只在这个包装中有效，[`IndexedHmm::state_id`] 只返回已经分配的编号，不会为其它字符串分配编号
+#[derive(Debug)]
+pub struct InternedHmm<H> {
+    hmm: H,
+    interner: RwLock<Interner>,
+}
+
+impl<H: Hmm> InternedHmm<H> {
+    pub fn new(hmm: H) -> Self {
+        Self {
+            hmm,
+            interner: RwLock::new(Interner::default()),
+        }
+    }
+
+    pub fn inner(&self) -> &H {
+        &self.hmm
+    }
+
+    /// 读取映射，锁被污染时仍然使用其中的数据
+    fn read(&self) -> RwLockReadGuard<'_, Interner> {
+        self.interner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<H: Hmm> IndexedHmm for InternedHmm<H> {
+    /// 字符串版本的模型无法判断拼音是否存在，总是返回编号，不存在的拼音没有对应的汉字
+    fn observation_id(&self, observation: &str) -> Option<ObsId> {
+        if let Some(id) = self.read().obs_ids.get(observation) {
+            return Some(*id);
+        }
+        let states = self.hmm.get_states(observation);
+        let mut interner = self
+            .interner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        // 在等待写锁时可能已经被其它线程添加
+        if let Some(id) = interner.obs_ids.get(observation) {
+            return Some(*id);
+        }
+        let states = states
+            .iter()
+            .map(|state| interner.intern_state(state))
+            .collect();
+        let id = ObsId(interner.observations.len() as u32);
+        interner.observations.push(observation.to_owned());
+        interner.obs_states.push(states);
+        interner.obs_ids.insert(observation.to_owned(), id);
+        Some(id)
+    }
+
+    /// 只查询已经分配的编号，避免任意的上下文占用内存
+    fn state_id(&self, state: &str) -> Option<StateId> {
+        self.read().state_ids.get(state).copied()
+    }
+
+    fn state_name(&self, state: StateId) -> Cow<'_, str> {
+        Cow::Owned(self.read().states[state.0 as usize].clone())
+    }
+
+    fn states(&self, observation: ObsId) -> Cow<'_, [StateId]> {
+        Cow::Owned(self.read().obs_states[observation.0 as usize].clone())
+    }
+
+    fn start(&self, state: StateId) -> f64 {
+        self.hmm.start(&self.read().states[state.0 as usize])
+    }
+
+    fn emission(&self, state: StateId, observation: ObsId) -> f64 {
+        let interner = self.read();
+        self.hmm.emission(
+            &interner.states[state.0 as usize],
+            &interner.observations[observation.0 as usize],
+        )
+    }
+
+    fn transition(&self, from_state: StateId, to_state: StateId) -> f64 {
+        let interner = self.read();
+        self.hmm.transition(
+            &interner.states[from_state.0 as usize],
+            &interner.states[to_state.0 as usize],
+        )
+    }
+
+    /// 直接使用字符串查询被包装的模型，不需要为 from_state 分配编号
+    fn transition_from_name(&self, from_state: &str, to_state: StateId) -> f64 {
+        self.hmm
+            .transition(from_state, &self.read().states[to_state.0 as usize])
+    }
+}
@@ -0,0 +1,94 @@
+use crate::converter::Candidate;
+use crate::learning::{self, LearningHistory, SharedHistory};
+use pinyinchch_type::hmm::Hmm;
+use std::sync::RwLockReadGuard;
+
+/// 根据用户的选择调整概率的 HMM
+///
+/// 选择过的 (拼音, 汉字) 提升发射概率，选择过的相邻汉字提升转移概率，提升的方式见 [`LearningHistory::weight`]
+///
+/// 直接作为 [`Hmm`] 使用时每次查询概率都会获取一次读锁，解码时应该使用 [`LearnedHmm::snapshot`]，
+/// 整个解码过程只获取一次读锁
+#[derive(Debug, Clone)]
+pub struct LearnedHmm<H> {
+    hmm: H,
+    history: SharedHistory,
+}
+
+impl<H: Hmm> LearnedHmm<H> {
+    pub fn new(hmm: H, history: SharedHistory) -> Self {
+        Self { hmm, history }
+    }
+
+    pub fn inner(&self) -> &H {
+        &self.hmm
+    }
+
+    pub fn history(&self) -> &SharedHistory {
+        &self.history
+    }
+
+    /// 获取学习记录的读锁，返回的 HMM 在查询概率时不再获取锁
+    ///
+    /// 持有快照时无法记录新的选择，应该只在一次解码的过程中持有
+    pub fn snapshot(&self) -> LearnedHmmSnapshot<'_, H> {
+        LearnedHmmSnapshot {
+            hmm: &self.hmm,
+            history: learning::read(&self.history),
+        }
+    }
+
+    /// 记录一次选择，见 [`LearningHistory::record`]
+    pub fn record(&self, pinyins: &[&str], hanzi: &str) -> bool {
+        learning::write(&self.history).record(pinyins, hanzi)
+    }
+
+    /// 记录用户选择的候选结果，见 [`LearningHistory::record_candidate`]
+    pub fn record_candidate(&self, candidate: &Candidate) {
+        learning::write(&self.history).record_candidate(candidate);
+    }
+}
+
+impl<H: Hmm> Hmm for LearnedHmm<H> {
+    fn start(&self, state: &str) -> f64 {
+        self.hmm.start(state)
+    }
+
+    fn emission(&self, state: &str, observation: &str) -> f64 {
+        self.snapshot().emission(state, observation)
+    }
+
+    fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+        self.snapshot().transition(from_state, to_state)
+    }
+
+    fn get_states(&self, observation: &str) -> Vec<String> {
+        self.hmm.get_states(observation)
+    }
+}
+
+/// 持有学习记录读锁的 [`LearnedHmm`]，见 [`LearnedHmm::snapshot`]
+pub struct LearnedHmmSnapshot<'a, H> {
+    hmm: &'a H,
+    history: RwLockReadGuard<'a, LearningHistory>,
+}
+
+impl<H: Hmm> Hmm for LearnedHmmSnapshot<'_, H> {
+    fn start(&self, state: &str) -> f64 {
+        self.hmm.start(state)
+    }
+
+    fn emission(&self, state: &str, observation: &str) -> f64 {
+        let weight = self.history.weight(&[observation], state);
+        LearningHistory::boost(self.hmm.emission(state, observation), weight)
+    }
+
+    fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+        let weight = self.history.transition_weight(from_state, to_state);
+        LearningHistory::boost(self.hmm.transition(from_state, to_state), weight)
+    }
+
+    fn get_states(&self, observation: &str) -> Vec<String> {
+        self.hmm.get_states(observation)
+    }
+}
@@ -1,9 +1,24 @@
-use crate::priority::{Item, PrioritySet};
+mod fuzzy;
+mod indexed;
+mod learning;
+mod tone;
+
+use crate::converter::DecodeOptions;
+pub use crate::hmm::fuzzy::FuzzyHmm;
+pub use crate::hmm::indexed::{InternedHmm, viterbi_indexed, viterbi_indexed_with_context};
+pub use crate::hmm::learning::{LearnedHmm, LearnedHmmSnapshot};
+pub use crate::hmm::tone::TonedHmm;
+pub use crate::pinyin::DEFAULT_TONE_PENALTY;
+use crate::pinyin::PinyinLattice;
+use crate::priority::Item;
 use pinyinchch_type::hmm::Hmm;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 
 /// 使用 Viterbi 算法，实现拼音转汉字
 ///
+/// 每个状态保留最优的 path_num 条路径，只记录回溯指针，最后按分数降序返回汉字互不相同的最多 path_num 个结果
+///
 /// * `hmm` - HMM 实现
 /// * `pinyin_seq` - 需要转换的拼音序列
 /// * `path_num` - 返回路径数量
@@ -15,108 +30,592 @@ pub fn viterbi(
     path_num: usize,
     use_log_prob: bool,
     min_prob: f64,
+) -> Vec<Item> {
+    viterbi_with_context(hmm, None, pinyin_seq, path_num, use_log_prob, min_prob)
+}
+
+/// 使用 Viterbi 算法，实现拼音转汉字，context 是已经确定的前一个汉字
+///
+/// 有 context 时，第一个拼音使用从 context 出发的转移概率代替起始概率，
+/// 返回的路径中不包含 context
+///
+/// * `hmm` - HMM 实现
+/// * `context` - 已经确定的前一个汉字
+/// * `pinyin_seq` - 需要转换的拼音序列
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `min_prob` - 最小概率值，防止概率为0
+pub fn viterbi_with_context(
+    hmm: &impl Hmm,
+    context: Option<&str>,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+    min_prob: f64,
+) -> Vec<Item> {
+    viterbi_with_options(
+        hmm,
+        context,
+        pinyin_seq,
+        path_num,
+        use_log_prob,
+        min_prob,
+        &DecodeOptions::default(),
+    )
+}
+
+/// 使用 Viterbi 算法，实现拼音转汉字，options 限制每个位置考虑和保留的状态数量以及时间
+///
+/// 超过时间限制时返回已经计算完的拼音的结果，只覆盖前面的拼音
+///
+/// * `hmm` - HMM 实现
+/// * `context` - 已经确定的前一个汉字
+/// * `pinyin_seq` - 需要转换的拼音序列
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `min_prob` - 最小概率值，防止概率为0
+/// * `options` - 搜索范围和时间的限制
+pub fn viterbi_with_options(
+    hmm: &impl Hmm,
+    context: Option<&str>,
+    pinyin_seq: &[&str],
+    path_num: usize,
+    use_log_prob: bool,
+    min_prob: f64,
+    options: &DecodeOptions,
 ) -> Vec<Item> {
     if pinyin_seq.is_empty() {
         return Vec::new();
     }
+    let deadline = options.deadline();
+
+    // 有 context 时，context 作为第一个拼音之前的唯一状态
+    let context = context.map(|context| ViterbiColumn::context(context.to_owned(), use_log_prob));
+    let mut columns: Vec<ViterbiColumn> = Vec::with_capacity(pinyin_seq.len());
+    for cur_obs in pinyin_seq {
+        let prev = columns.last().or(context.as_ref());
+        let column = viterbi_step(
+            hmm,
+            prev,
+            cur_obs,
+            path_num,
+            use_log_prob,
+            min_prob,
+            options,
+        );
+        columns.push(column);
+        if DecodeOptions::expired(deadline) {
+            break;
+        }
+    }
 
-    // 存储到达时刻 time 状态 state 的最优路径
-    let mut time_and_state = Vec::<HashMap<String, PrioritySet>>::new();
+    viterbi_result(&columns, path_num, String::clone)
+}
 
-    let time = 0;
-    let cur_obs = pinyin_seq[time];
+/// 到达一个状态的一条路径，只记录分数和回溯指针，路径在最后才会还原
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    score: f64,
+    /// 这一步的局部概率
+    prob: f64,
+    /// 前一个时刻的 (状态下标, 排名)
+    back: Option<(usize, usize)>,
+}
 
-    // 初始化基础情况 (t == 0)
-    let mut prev_states = hmm.get_states(cur_obs);
-    let cur_states = prev_states.clone();
+/// Viterbi 算法中一个时刻的结果，每个状态保留按分数降序排列的最多 path_num 条路径
+///
+/// 增量解码时 [`HmmDecoder`](crate::converter::HmmDecoder) 为每个拼音保存的一列
+#[derive(Debug, Clone)]
+pub struct ViterbiColumn<S = String> {
+    states: Vec<S>,
+    entries: Vec<Vec<Entry>>,
+}
 
-    let mut initial_map = HashMap::new();
-    for state in &cur_states {
-        let start_prob = hmm.start(state);
-        let emission_prob = hmm.emission(state, cur_obs);
-        let score = if use_log_prob {
-            f64::max(start_prob, min_prob).ln() + f64::max(emission_prob, min_prob).ln()
-        } else {
-            f64::max(start_prob, min_prob) * f64::max(emission_prob, min_prob)
-        };
+/// 合并候选路径时堆中的元素，按分数比较
+struct HeapEntry {
+    score: f64,
+    state: usize,
+    rank: usize,
+}
 
-        let path = vec![state.clone()];
-        let mut ps = PrioritySet::new(path_num);
-        ps.put(score, path);
-        initial_map.insert(state.clone(), ps);
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score
     }
+}
 
-    time_and_state.push(initial_map);
-
-    // 运行 t > 0 的Viterbi算法
-    for t in 1..pinyin_seq.len() {
-        let cur_obs = &pinyin_seq[t];
-        // 优化内存使用：只保留前一个时刻的结果
-        if time_and_state.len() == 2 {
-            time_and_state = vec![time_and_state[time_and_state.len() - 1].clone()];
-        }
+impl Eq for HeapEntry {}
 
-        let mut next_map = HashMap::new();
-        let prev_states_clone = prev_states.clone();
-        let cur_states = hmm.get_states(cur_obs);
-        prev_states = cur_states.clone();
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-        for y in &cur_states {
-            let mut ps = PrioritySet::new(path_num);
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .partial_cmp(&other.score)
+            .unwrap_or(Ordering::Equal)
+    }
+}
 
-            for y0 in &prev_states_clone {
-                if let Some(prev_ps) = time_and_state[0].get(y0) {
-                    for item in prev_ps.iter() {
-                        let transition_prob = hmm.transition(y0, y);
-                        let emission_prob = hmm.emission(y, cur_obs);
+impl<S> ViterbiColumn<S> {
+    /// 只有一个状态的列，作为第一个拼音之前的上下文
+    fn context(state: S, use_log_prob: bool) -> Self {
+        Self {
+            states: vec![state],
+            entries: vec![vec![Entry {
+                score: if use_log_prob { 0.0 } else { 1.0 },
+                prob: 1.0,
+                back: None,
+            }]],
+        }
+    }
 
-                        let new_score = if use_log_prob {
-                            item.score()
-                                + f64::max(transition_prob, min_prob).ln()
-                                + f64::max(emission_prob, min_prob).ln()
-                        } else {
-                            item.score()
-                                * f64::max(transition_prob, min_prob)
-                                * f64::max(emission_prob, min_prob)
-                        };
+    /// 第一个时刻的列，probs 返回每个状态的 (起始概率, 发射概率)
+    fn first(states: Vec<S>, probs: impl Fn(&S) -> (f64, f64), use_log_prob: bool) -> Self {
+        let entries = states
+            .iter()
+            .map(|state| {
+                let (start_prob, emission_prob) = probs(state);
+                let prob = start_prob * emission_prob;
+                let score = if use_log_prob {
+                    start_prob.ln() + emission_prob.ln()
+                } else {
+                    prob
+                };
+                vec![Entry {
+                    score,
+                    prob,
+                    back: None,
+                }]
+            })
+            .collect();
+        Self { states, entries }
+    }
 
-                        let mut new_path = item.path().clone();
-                        new_path.push(y.clone());
+    /// 从这一列转移到下一个时刻的 states
+    ///
+    /// 这一列每个状态的路径已经按分数降序排列，加上同一个转移概率之后仍然有序，
+    /// 所以只需要用堆合并这些有序列表，取出前 path_num 条
+    fn next(
+        &self,
+        states: Vec<S>,
+        emission: impl Fn(&S) -> f64,
+        transition: impl Fn(&S, &S) -> f64,
+        path_num: usize,
+        use_log_prob: bool,
+    ) -> Self {
+        let mut entries = Vec::with_capacity(states.len());
+        // 从这一列的每个状态转移过来的 (局部概率, 对数概率)
+        let mut local_probs = vec![(0.0, 0.0); self.states.len()];
+        let combine = |score: f64, (prob, log_prob): (f64, f64)| {
+            if use_log_prob {
+                score + log_prob
+            } else {
+                score * prob
+            }
+        };
+        for state in &states {
+            let emission_prob = emission(state);
+            let mut heap = BinaryHeap::with_capacity(self.states.len());
+            for (idx, prev_state) in self.states.iter().enumerate() {
+                let transition_prob = transition(prev_state, state);
+                local_probs[idx] = (
+                    transition_prob * emission_prob,
+                    transition_prob.ln() + emission_prob.ln(),
+                );
+                if let Some(first) = self.entries[idx].first() {
+                    heap.push(HeapEntry {
+                        score: combine(first.score, local_probs[idx]),
+                        state: idx,
+                        rank: 0,
+                    });
+                }
+            }
 
-                        ps.put(new_score, new_path);
-                    }
+            let mut best = Vec::with_capacity(path_num);
+            while best.len() < path_num
+                && let Some(HeapEntry { score, state, rank }) = heap.pop()
+            {
+                best.push(Entry {
+                    score,
+                    prob: local_probs[state].0,
+                    back: Some((state, rank)),
+                });
+                if let Some(next) = self.entries[state].get(rank + 1) {
+                    heap.push(HeapEntry {
+                        score: combine(next.score, local_probs[state]),
+                        state,
+                        rank: rank + 1,
+                    });
                 }
             }
+            entries.push(best);
+        }
+        Self { states, entries }
+    }
+
+    /// 只保留最优路径分数最高的 beam_width 个状态，没有路径的状态也会被去掉
+    fn prune(self, beam_width: usize) -> Self {
+        let (states, entries) = keep_top(
+            self.states.into_iter().zip(self.entries).collect(),
+            beam_width,
+            |(_, entries)| {
+                entries
+                    .first()
+                    .map_or(f64::NEG_INFINITY, |entry| entry.score)
+            },
+        )
+        .into_iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .unzip();
+        Self { states, entries }
+    }
+}
 
-            next_map.insert(y.clone(), ps);
+/// 保留 key 最大的 n 个元素，不改变它们原来的顺序
+fn keep_top<T>(items: Vec<T>, n: usize, key: impl Fn(&T) -> f64) -> Vec<T> {
+    if items.len() <= n {
+        return items;
+    }
+    let mut order = (0..items.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| {
+        key(&items[*b])
+            .partial_cmp(&key(&items[*a]))
+            .unwrap_or(Ordering::Equal)
+    });
+    let mut keep = vec![false; items.len()];
+    for idx in order.into_iter().take(n) {
+        keep[idx] = true;
+    }
+    items
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(item, keep)| keep.then_some(item))
+        .collect()
+}
+
+/// 计算 Viterbi 算法在当前时刻到达每个状态的最优的 path_num 条路径
+///
+/// prev 是前一个时刻的结果，第一个时刻为 None，此时使用起始概率
+pub(crate) fn viterbi_step(
+    hmm: &impl Hmm,
+    prev: Option<&ViterbiColumn>,
+    cur_obs: &str,
+    path_num: usize,
+    use_log_prob: bool,
+    min_prob: f64,
+    options: &DecodeOptions,
+) -> ViterbiColumn {
+    let emission = |state: &String| f64::max(hmm.emission(state, cur_obs), min_prob);
+    let mut states = hmm.get_states(cur_obs);
+    if let Some(max_states) = options.max_states {
+        states = keep_top(states, max_states, emission);
+    }
+    let column = match prev {
+        None => ViterbiColumn::first(
+            states,
+            |state| (f64::max(hmm.start(state), min_prob), emission(state)),
+            use_log_prob,
+        ),
+        Some(prev) => prev.next(
+            states,
+            emission,
+            |from, to| f64::max(hmm.transition(from, to), min_prob),
+            path_num,
+            use_log_prob,
+        ),
+    };
+    match options.beam_width {
+        Some(beam_width) => column.prune(beam_width),
+        None => column,
+    }
+}
+
+/// 从最后一个时刻回溯，按分数降序返回 path_num 个汉字互不相同的结果，name 把状态转换成汉字
+pub(crate) fn viterbi_result<S>(
+    columns: &[ViterbiColumn<S>],
+    path_num: usize,
+    name: impl Fn(&S) -> String,
+) -> Vec<Item> {
+    let Some(last) = columns.last() else {
+        return Vec::new();
+    };
+    let mut finals = last
+        .entries
+        .iter()
+        .enumerate()
+        .flat_map(|(state, entries)| (0..entries.len()).map(move |rank| (state, rank)))
+        .collect::<Vec<_>>();
+    finals.sort_by(|a, b| {
+        let a = last.entries[a.0][a.1].score;
+        let b = last.entries[b.0][b.1].score;
+        b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+    });
+
+    let mut seen = HashSet::new();
+    let mut result = Vec::with_capacity(path_num);
+    for (state, rank) in finals {
+        if result.len() >= path_num {
+            break;
+        }
+        let score = last.entries[state][rank].score;
+        let mut path = Vec::with_capacity(columns.len());
+        let mut spans = Vec::with_capacity(columns.len());
+        let mut cursor = Some((state, rank));
+        for (t, column) in columns.iter().enumerate().rev() {
+            let Some((state, rank)) = cursor else {
+                break;
+            };
+            let entry = column.entries[state][rank];
+            path.push(name(&column.states[state]));
+            spans.push((t..t + 1, entry.prob));
+            // 第一个时刻的回溯指针指向 context，不需要继续回溯
+            cursor = if t > 0 { entry.back } else { None };
+        }
+        path.reverse();
+        spans.reverse();
+        if seen.insert(path.concat()) {
+            result.push(Item::new(score, path, spans));
         }
+    }
+    result
+}
 
-        time_and_state.push(next_map);
+/// 在拼音网格上使用 Viterbi 算法，同时考虑所有的拼音切分方式
+///
+/// 返回的结果中，相同的汉字只保留分数最高的一个，`Item::pinyins` 记录了每个结果使用的拼音切分
+///
+/// * `hmm` - HMM 实现
+/// * `lattice` - 拼音网格
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `min_prob` - 最小概率值，防止概率为0
+pub fn viterbi_lattice(
+    hmm: &impl Hmm,
+    lattice: &PinyinLattice,
+    path_num: usize,
+    use_log_prob: bool,
+    min_prob: f64,
+) -> Vec<Item> {
+    viterbi_lattice_with_options(
+        hmm,
+        lattice,
+        path_num,
+        use_log_prob,
+        min_prob,
+        &DecodeOptions::default(),
+    )
+}
+
+/// 在拼音网格上使用 Viterbi 算法，options 限制每个节点考虑和保留的状态数量以及时间
+///
+/// 超过时间限制时返回已经计算完的最后一个有结果的节点的结果，只覆盖前面的拼音
+///
+/// * `hmm` - HMM 实现
+/// * `lattice` - 拼音网格
+/// * `path_num` - 返回路径数量
+/// * `use_log_prob` - 是否使用对数概率
+/// * `min_prob` - 最小概率值，防止概率为0
+/// * `options` - 搜索范围和时间的限制
+pub fn viterbi_lattice_with_options(
+    hmm: &impl Hmm,
+    lattice: &PinyinLattice,
+    path_num: usize,
+    use_log_prob: bool,
+    min_prob: f64,
+    options: &DecodeOptions,
+) -> Vec<Item> {
+    if lattice.is_empty() {
+        return Vec::new();
     }
+    let node_num = lattice.len();
+    let deadline = options.deadline();
+
+    // 到达每个节点的路径，第一个节点是起点，没有状态
+    let mut nodes = (0..node_num)
+        .map(|_| LatticeNode::default())
+        .collect::<Vec<_>>();
+    let mut end_node = node_num - 1;
+
+    for from_node in 0..node_num - 1 {
+        if from_node > 0 && nodes[from_node].column.states.is_empty() {
+            continue;
+        }
+        if let Some(beam_width) = options.beam_width {
+            let node = std::mem::take(&mut nodes[from_node]);
+            nodes[from_node] = node.prune(beam_width);
+        }
 
-    // 收集最终结果
-    let mut result = PrioritySet::new(path_num);
-    if let Some(final_map) = time_and_state.last() {
-        for (_state, ps) in final_map {
-            for item in ps.iter() {
-                result.put(item.score(), item.path().clone());
+        for (edge, (to_node, cur_obs)) in lattice.edges(from_node).iter().enumerate() {
+            let prev = (from_node > 0).then(|| &nodes[from_node].column);
+            let step = viterbi_step(
+                hmm,
+                prev,
+                cur_obs,
+                path_num,
+                use_log_prob,
+                min_prob,
+                options,
+            );
+            nodes[*to_node].merge(step, (from_node, edge), path_num);
+        }
+        // 之后的边都从更后面的节点出发，from_node + 1 之前的节点都已经计算完
+        if DecodeOptions::expired(deadline) {
+            end_node = (1..=from_node + 1)
+                .rev()
+                .find(|node| !nodes[*node].column.states.is_empty())
+                .unwrap_or(from_node + 1);
+            break;
+        }
+    }
+
+    lattice_result(lattice, &nodes, end_node, path_num)
+}
+
+/// 拼音网格中到达一个节点的路径，和 [`ViterbiColumn`] 相同，
+/// 另外记录每条路径经过的最后一条边 (起点节点, 边的下标)，回溯指针指向起点节点的列
+#[derive(Debug)]
+struct LatticeNode {
+    column: ViterbiColumn,
+    index: HashMap<String, usize>,
+    origins: Vec<Vec<(usize, usize)>>,
+}
+
+impl Default for LatticeNode {
+    fn default() -> Self {
+        Self {
+            column: ViterbiColumn {
+                states: Vec::new(),
+                entries: Vec::new(),
+            },
+            index: HashMap::new(),
+            origins: Vec::new(),
+        }
+    }
+}
+
+impl LatticeNode {
+    /// 合并经过 origin 这条边到达这个节点的路径，每个状态保留分数最高的 path_num 条
+    fn merge(&mut self, step: ViterbiColumn, origin: (usize, usize), path_num: usize) {
+        for (state, entries) in step.states.into_iter().zip(step.entries) {
+            let idx = *self.index.entry(state.clone()).or_insert_with(|| {
+                self.column.states.push(state);
+                self.column.entries.push(Vec::new());
+                self.origins.push(Vec::new());
+                self.column.states.len() - 1
+            });
+            // 两个列表都已经按分数降序排列
+            let old_entries = std::mem::take(&mut self.column.entries[idx]);
+            let old_origins = std::mem::take(&mut self.origins[idx]);
+            let mut old = old_entries.into_iter().zip(old_origins).peekable();
+            let mut new = entries.into_iter().map(|entry| (entry, origin)).peekable();
+            while self.column.entries[idx].len() < path_num {
+                let take_old = match (old.peek(), new.peek()) {
+                    (Some((a, _)), Some((b, _))) => a.score >= b.score,
+                    (Some(_), None) => true,
+                    (None, Some(_)) => false,
+                    (None, None) => break,
+                };
+                let (entry, origin) = if take_old { old.next() } else { new.next() }.unwrap();
+                self.column.entries[idx].push(entry);
+                self.origins[idx].push(origin);
             }
         }
     }
 
-    let mut result_vec: Vec<Item> = result.to_sorted_vec();
-    result_vec.sort_by(|a, b| {
-        b.score()
-            .partial_cmp(&a.score())
-            .unwrap_or(std::cmp::Ordering::Equal)
+    /// 只保留最优路径分数最高的 beam_width 个状态，见 [`ViterbiColumn::prune`]
+    fn prune(self, beam_width: usize) -> Self {
+        let kept = keep_top(
+            self.column
+                .states
+                .into_iter()
+                .zip(self.column.entries)
+                .zip(self.origins)
+                .collect(),
+            beam_width,
+            |((_, entries), _)| {
+                entries
+                    .first()
+                    .map_or(f64::NEG_INFINITY, |entry| entry.score)
+            },
+        );
+        let mut node = Self::default();
+        for ((state, entries), origins) in kept {
+            node.index.insert(state.clone(), node.column.states.len());
+            node.column.states.push(state);
+            node.column.entries.push(entries);
+            node.origins.push(origins);
+        }
+        node
+    }
+}
+
+/// 从 end_node 回溯，按分数降序返回 path_num 个汉字互不相同的结果
+fn lattice_result(
+    lattice: &PinyinLattice,
+    nodes: &[LatticeNode],
+    end_node: usize,
+    path_num: usize,
+) -> Vec<Item> {
+    let last = &nodes[end_node];
+    let mut finals = last
+        .column
+        .entries
+        .iter()
+        .enumerate()
+        .flat_map(|(state, entries)| (0..entries.len()).map(move |rank| (state, rank)))
+        .collect::<Vec<_>>();
+    finals.sort_by(|a, b| {
+        let a = last.column.entries[a.0][a.1].score;
+        let b = last.column.entries[b.0][b.1].score;
+        b.partial_cmp(&a).unwrap_or(Ordering::Equal)
     });
 
-    result_vec
+    let mut seen = HashSet::new();
+    let mut result = Vec::with_capacity(path_num);
+    for (state, rank) in finals {
+        if result.len() >= path_num {
+            break;
+        }
+        let score = last.column.entries[state][rank].score;
+        let mut path = Vec::new();
+        let mut probs = Vec::new();
+        let mut pinyins = Vec::new();
+        let mut cursor = Some((end_node, state, rank));
+        while let Some((node, state, rank)) = cursor {
+            let entry = nodes[node].column.entries[state][rank];
+            let (from_node, edge) = nodes[node].origins[state][rank];
+            path.push(nodes[node].column.states[state].clone());
+            probs.push(entry.prob);
+            pinyins.push(lattice.edges(from_node)[edge].1.clone());
+            // 从起点出发的边没有前一个状态
+            cursor = (from_node > 0)
+                .then_some(entry.back)
+                .flatten()
+                .map(|(state, rank)| (from_node, state, rank));
+        }
+        path.reverse();
+        pinyins.reverse();
+        let spans = probs
+            .into_iter()
+            .rev()
+            .enumerate()
+            .map(|(t, prob)| (t..t + 1, prob))
+            .collect();
+        if seen.insert(path.concat()) {
+            result.push(Item::new(score, path, spans).with_pinyins(pinyins));
+        }
+    }
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     // 为测试创建一个简单的HMM参数实现
     #[allow(unused)]
@@ -159,6 +658,10 @@ mod tests {
             let mut py2hz_map = HashMap::new();
             py2hz_map.insert("ni".to_string(), vec!["你".to_string()]);
             py2hz_map.insert("wo".to_string(), vec!["我".to_string()]);
+            py2hz_map.insert(
+                "hello".to_string(),
+                vec!["你".to_string(), "我".to_string()],
+            );
 
             TestHmm {
                 states: vec!["你".to_string(), "我".to_string()],
@@ -208,5 +711,247 @@ mod tests {
 
         assert!(!result.is_empty());
         assert_eq!(result[0].path(), &vec!["你".to_string()]);
+        assert_eq!(result[0].spans(), &vec![(0..1, 0.6 * 0.8)]);
+    }
+
+    #[test]
+    fn test_viterbi_n_best() {
+        let hmm = TestHmm::new();
+        let observations = ["hello", "hello", "hello"];
+        let result = viterbi(&hmm, &observations, 4, false, 0.0);
+
+        // 枚举所有的路径得到前 4 个
+        let mut expected = Vec::new();
+        for path in 0..8 {
+            let path = (0..3)
+                .map(|t| if path >> t & 1 == 0 { "你" } else { "我" })
+                .collect::<Vec<_>>();
+            let mut prob = hmm.start(path[0]) * hmm.emission(path[0], "hello");
+            for t in 1..3 {
+                prob *= hmm.transition(path[t - 1], path[t]) * hmm.emission(path[t], "hello");
+            }
+            expected.push((path.concat(), prob));
+        }
+        expected.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        assert_eq!(result.len(), 4);
+        for (item, (text, prob)) in result.iter().zip(&expected) {
+            assert_eq!(&item.path().concat(), text);
+            assert!((item.score() - prob).abs() < 1e-12);
+            let spans_prob = item.spans().iter().map(|(_, prob)| prob).product::<f64>();
+            assert!((spans_prob - prob).abs() < 1e-12);
+        }
+
+        // 重复的状态产生相同的汉字，结果中只保留一个
+        let mut hmm = TestHmm::new();
+        hmm.py2hz_map.insert(
+            "hello".to_string(),
+            vec!["你".to_string(), "你".to_string(), "我".to_string()],
+        );
+        let result = viterbi(&hmm, &observations, 8, true, 3.14e-200);
+        let texts = result
+            .iter()
+            .map(|item| item.path().concat())
+            .collect::<HashSet<_>>();
+        assert_eq!(result.len(), 8);
+        assert_eq!(texts.len(), 8);
+        assert!(result.windows(2).all(|w| w[0].score() >= w[1].score()));
+    }
+
+    #[test]
+    fn test_viterbi_with_options() {
+        use std::time::Duration;
+
+        let hmm = TestHmm::new();
+        let observations = ["hello", "hello", "hello"];
+        let full = viterbi(&hmm, &observations, 8, true, 3.14e-200);
+        assert_eq!(full.len(), 8);
+
+        // 每个位置只考虑发射概率最大的 我
+        let options = DecodeOptions {
+            max_states: Some(1),
+            ..Default::default()
+        };
+        let result = viterbi_with_options(&hmm, None, &observations, 8, true, 3.14e-200, &options);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path().concat(), "我我我");
+
+        // 每个位置只保留一个状态时，只有一条贪心的路径
+        let options = DecodeOptions {
+            beam_width: Some(1),
+            ..Default::default()
+        };
+        let result = viterbi_with_options(&hmm, None, &observations, 8, true, 3.14e-200, &options);
+        assert_eq!(result.len(), 1);
+        assert!(full.iter().any(|item| item.path() == result[0].path()));
+
+        let options = DecodeOptions {
+            time_limit: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let result = viterbi_with_options(&hmm, None, &observations, 8, true, 3.14e-200, &options);
+        assert!(result.iter().all(|item| item.path().len() == 1));
+    }
+
+    #[test]
+    fn test_viterbi_indexed() {
+        use pinyinchch_type::hmm::IndexedHmm;
+
+        let summary = |items: Vec<Item>| {
+            items
+                .into_iter()
+                .map(|item| (item.score(), item.path().clone(), item.spans().clone()))
+                .collect::<Vec<_>>()
+        };
+        let indexed = InternedHmm::new(TestHmm::new());
+        let hmm = indexed.inner();
+        for observations in [&["ni"][..], &["ni", "hello"], &["hello", "wo", "hello"]] {
+            for use_log_prob in [false, true] {
+                let expected = viterbi(hmm, observations, 3, use_log_prob, 3.14e-200);
+                let result = viterbi_indexed(&indexed, observations, 3, use_log_prob, 3.14e-200);
+                assert_eq!(summary(result), summary(expected));
+            }
+        }
+        // 还没有分配编号的上下文和模型中没有的上下文都和字符串版本一致
+        for context in ["我", "他", "xyz"] {
+            for use_log_prob in [false, true] {
+                let expected = summary(viterbi_with_context(
+                    hmm,
+                    Some(context),
+                    &["hello"],
+                    2,
+                    use_log_prob,
+                    1e-10,
+                ));
+                let fresh = InternedHmm::new(TestHmm::new());
+                for indexed in [&indexed, &fresh] {
+                    let result = viterbi_indexed_with_context(
+                        indexed,
+                        Some(context),
+                        &["hello"],
+                        2,
+                        use_log_prob,
+                        1e-10,
+                    );
+                    assert_eq!(summary(result), expected);
+                }
+            }
+        }
+        // 查询上下文不会分配编号
+        assert_eq!(indexed.state_id("xyz"), None);
+
+        // 编号在包装中保持不变
+        let ni = indexed.state_id("你").unwrap();
+        assert_eq!(indexed.state_name(ni), "你");
+        let hello = indexed.observation_id("hello").unwrap();
+        assert_eq!(indexed.states(hello).len(), 2);
+        assert_eq!(indexed.observation_id("hello"), Some(hello));
+        assert!(viterbi_indexed(&indexed, &["ni", "xyz"], 3, true, 3.14e-200).is_empty());
+    }
+
+    #[test]
+    fn test_viterbi_lattice() {
+        let mut params = TestHmm::new();
+        params
+            .py2hz_map
+            .insert("xi".to_string(), vec!["西".to_string()]);
+        params
+            .py2hz_map
+            .insert("an".to_string(), vec!["安".to_string()]);
+        params
+            .py2hz_map
+            .insert("xian".to_string(), vec!["先".to_string()]);
+        let lattice = crate::pinyin::pinyin_lattice("xian");
+        let result = viterbi_lattice(&params, &lattice, 3, true, 3.14e-200);
+        let texts = result
+            .iter()
+            .map(|item| item.path().concat())
+            .collect::<HashSet<_>>();
+        assert_eq!(texts, HashSet::from(["西安".to_string(), "先".to_string()]));
+        let xi_an = result.iter().find(|item| item.path().len() == 2).unwrap();
+        assert_eq!(xi_an.pinyins(), &vec!["xi".to_string(), "an".to_string()]);
+        assert_eq!(xi_an.spans()[1].0, 1..2);
+
+        // 超时之后返回已经计算完的部分结果
+        let options = DecodeOptions {
+            time_limit: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        let result = viterbi_lattice_with_options(&params, &lattice, 3, true, 3.14e-200, &options);
+        assert_eq!(result[0].path(), &vec!["西".to_string()]);
+        assert_eq!(result[0].pinyins(), &vec!["xi".to_string()]);
+
+        // ji nan 和 jin an 都得到 "金安"，只保留分数最高的一个
+        for (pinyin, hanzi) in [("ji", "金"), ("jin", "金"), ("nan", "安")] {
+            params
+                .py2hz_map
+                .insert(pinyin.to_string(), vec![hanzi.to_string()]);
+        }
+        let lattice = crate::pinyin::pinyin_lattice("jinan");
+        let result = viterbi_lattice(&params, &lattice, 3, true, 3.14e-200);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path().concat(), "金安");
+        let options = DecodeOptions {
+            beam_width: Some(1),
+            ..Default::default()
+        };
+        let result = viterbi_lattice_with_options(&params, &lattice, 3, true, 3.14e-200, &options);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].spans().len(), 2);
+    }
+
+    #[test]
+    fn test_fuzzy_hmm() {
+        use crate::pinyin::{FuzzyRule, FuzzyRules};
+
+        let rules = FuzzyRules::new().with_rule(FuzzyRule::NL);
+        let fuzzy = FuzzyHmm::new(TestHmm::new(), rules);
+        assert_eq!(fuzzy.get_states("li"), ["你"]);
+        assert_eq!(fuzzy.emission("你", "li"), 0.4);
+        assert_eq!(fuzzy.emission("你", "ni"), 0.8);
+        let result = viterbi(&fuzzy, &["li", "wo"], 1, false, 0.0);
+        assert_eq!(result[0].path(), &vec!["你".to_string(), "我".to_string()]);
+    }
+
+    #[test]
+    fn test_toned_hmm() {
+        use crate::pinyin::ToneTable;
+
+        let table = ToneTable::from_reader("你 ni3\n我 wo3\n".as_bytes()).unwrap();
+        let toned = TonedHmm::new(TestHmm::new(), table.clone());
+        assert_eq!(toned.emission("你", "ni3"), 0.8);
+        assert!((toned.emission("你", "ni2") - 0.08).abs() < 1e-9);
+        assert_eq!(toned.get_states("ni2"), ["你"]);
+        let toned = TonedHmm::new(TestHmm::new(), table).with_penalty(0.0);
+        assert!(toned.get_states("ni2").is_empty());
+        let result = viterbi(&toned, &["ni3", "wo3"], 1, false, 0.0);
+        assert_eq!(result[0].path(), &vec!["你".to_string(), "我".to_string()]);
+    }
+
+    #[test]
+    fn test_viterbi_with_context() {
+        let hmm = TestHmm::new();
+        // 上一个字决定了第一个拼音的转移概率
+        let result = viterbi_with_context(&hmm, Some("你"), &["hello"], 1, false, 0.0);
+        assert_eq!(result[0].path(), &vec!["你".to_string()]);
+        let result = viterbi_with_context(&hmm, Some("我"), &["hello"], 1, false, 0.0);
+        assert_eq!(result[0].path(), &vec!["我".to_string()]);
+        assert_eq!(result[0].spans(), &vec![(0..1, 0.6 * 0.3)]);
+    }
+
+    #[test]
+    fn test_learned_hmm() {
+        use crate::learning::LearningHistory;
+
+        let learned = LearnedHmm::new(TestHmm::new(), LearningHistory::new().into_shared());
+        let result = viterbi(&learned, &["ni", "hello"], 1, false, 0.0);
+        assert_eq!(result[0].path(), &vec!["你".to_string(), "你".to_string()]);
+        // 选择过 hello => 我 之后，发射概率和转移概率都会提升
+        assert!(learned.record(&["ni", "hello"], "你我"));
+        assert!(learned.transition("你", "我") > 0.3);
+        let result = viterbi(&learned.snapshot(), &["ni", "hello"], 1, false, 0.0);
+        assert_eq!(result[0].path(), &vec!["你".to_string(), "我".to_string()]);
+        // 不使用快照时结果相同
+        let result = viterbi(&learned, &["ni", "hello"], 1, false, 0.0);
+        assert_eq!(result[0].path(), &vec!["你".to_string(), "我".to_string()]);
     }
 }
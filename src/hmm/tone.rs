@@ -0,0 +1,70 @@
+use crate::pinyin::{DEFAULT_TONE_PENALTY, ToneTable, TonedPinyin};
+use pinyinchch_type::hmm::Hmm;
+
+/// 支持声调的 HMM，观测值可以带有声调，如 lv3
+///
+/// 去除声调之后查询内部的 HMM，读音的声调和输入不一致的汉字，发射概率会乘以惩罚系数，
+/// 惩罚系数为 0 时，这些汉字会被过滤掉
+#[derive(Debug, Clone)]
+pub struct TonedHmm<H> {
+    hmm: H,
+    table: ToneTable,
+    penalty: f64,
+}
+
+impl<H: Hmm> TonedHmm<H> {
+    pub fn new(hmm: H, table: ToneTable) -> Self {
+        Self {
+            hmm,
+            table,
+            penalty: DEFAULT_TONE_PENALTY,
+        }
+    }
+
+    /// 设置声调不一致时的惩罚系数，取值范围是 [0, 1]
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    pub fn inner(&self) -> &H {
+        &self.hmm
+    }
+
+    pub fn table(&self) -> &ToneTable {
+        &self.table
+    }
+}
+
+impl<H: Hmm> Hmm for TonedHmm<H> {
+    fn start(&self, state: &str) -> f64 {
+        self.hmm.start(state)
+    }
+
+    fn emission(&self, state: &str, observation: &str) -> f64 {
+        let observation = TonedPinyin::parse(observation);
+        let emission = self.hmm.emission(state, &observation.pinyin);
+        if self.table.mismatches(state, &[observation]) > 0 {
+            emission * self.penalty
+        } else {
+            emission
+        }
+    }
+
+    fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+        self.hmm.transition(from_state, to_state)
+    }
+
+    fn get_states(&self, observation: &str) -> Vec<String> {
+        let observation = TonedPinyin::parse(observation);
+        let states = self.hmm.get_states(&observation.pinyin);
+        if self.penalty > 0.0 {
+            return states;
+        }
+        let observations = [observation];
+        states
+            .into_iter()
+            .filter(|state| self.table.mismatches(state, &observations) == 0)
+            .collect()
+    }
+}
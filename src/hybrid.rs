@@ -0,0 +1,207 @@
+use crate::converter::{Candidate, ConvertOptions, Decoder};
+use crate::dag::{dispatch_lattice_with_edge, dispatch_with_edge};
+use crate::pinyin::PinyinLattice;
+use crate::priority::Item;
+use pinyinchch_type::dag::Dag;
+use pinyinchch_type::hmm::Hmm;
+
+/// 混合解码时两个模型的插值权重
+///
+/// 一条边的分数是 `dag * ln(词组概率) + hmm * ln(边界的转移概率)`，不使用对数概率时是对应的乘积
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HybridWeights {
+    pub dag: f64,
+    pub hmm: f64,
+}
+
+impl Default for HybridWeights {
+    fn default() -> Self {
+        Self { dag: 0.5, hmm: 0.5 }
+    }
+}
+
+/// 同时使用 DAG 和 HMM 的解码器
+///
+/// 和 [`dispatch`](crate::dag::dispatch) 一样在拼音上按照词组切分，每个词组的分数由 DAG 的词组概率和
+/// HMM 在词组边界上的概率插值得到：第一个词组使用起始概率，之后的词组使用上一个词组的最后一个字到这个词组的第一个字的转移概率
+#[derive(Debug, Clone)]
+pub struct HybridDecoder<D, H> {
+    dag: D,
+    hmm: H,
+    weights: HybridWeights,
+}
+
+impl<D: Dag, H: Hmm> HybridDecoder<D, H> {
+    pub fn new(dag: D, hmm: H) -> Self {
+        Self {
+            dag,
+            hmm,
+            weights: HybridWeights::default(),
+        }
+    }
+
+    pub fn with_weights(mut self, weights: HybridWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn dag(&self) -> &D {
+        &self.dag
+    }
+
+    pub fn hmm(&self) -> &H {
+        &self.hmm
+    }
+
+    pub fn weights(&self) -> HybridWeights {
+        self.weights
+    }
+
+    /// 使用动态规划算法解码，context 是上一个字，作为第一个词组的转移概率的起点
+    fn dispatch(
+        &self,
+        context: Option<&str>,
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Item> {
+        dispatch_with_edge(
+            &self.dag,
+            pinyin_seq,
+            options.path_num,
+            options.use_log_prob,
+            &options.decode,
+            true,
+            |prev_item, phrase, dag_prob| {
+                self.edge_score(context, prev_item, phrase, dag_prob, options.min_prob)
+            },
+        )
+    }
+
+    /// 一条边的对数分数，prev_item 是之前的路径，phrase 是这条边的词组
+    fn edge_score(
+        &self,
+        context: Option<&str>,
+        prev_item: &Item,
+        phrase: &str,
+        dag_prob: f64,
+        min_prob: f64,
+    ) -> f64 {
+        let prev_state = match prev_item.path().last() {
+            Some(prev) => prev.chars().last().map(String::from),
+            None => context.map(str::to_owned),
+        };
+        let first = phrase.chars().next().map(String::from).unwrap_or_default();
+        let hmm_prob = match &prev_state {
+            Some(prev_state) => self.hmm.transition(prev_state, &first),
+            None => self.hmm.start(&first),
+        };
+        // 两个概率都限制最小值，避免对数概率是负无穷
+        let dag_prob = f64::max(dag_prob, min_prob);
+        let hmm_prob = f64::max(hmm_prob, min_prob);
+        self.weights.dag * dag_prob.ln() + self.weights.hmm * hmm_prob.ln()
+    }
+}
+
+impl<D: Dag, H: Hmm> Decoder for HybridDecoder<D, H> {
+    fn decode(&self, pinyin_seq: &[&str], options: &ConvertOptions) -> Vec<Candidate> {
+        self.dispatch(None, pinyin_seq, options)
+            .into_iter()
+            .map(|item| Candidate::from_item(item, pinyin_seq))
+            .collect()
+    }
+
+    /// 使用 context 的最后一个字作为第一个词组的转移概率的起点
+    fn decode_with_context(
+        &self,
+        context: &str,
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate> {
+        let last = context.chars().last().map(String::from);
+        self.dispatch(last.as_deref(), pinyin_seq, options)
+            .into_iter()
+            .map(|item| Candidate::from_item(item, pinyin_seq))
+            .collect()
+    }
+
+    /// 和 [`dispatch_lattice_with_options`](crate::dag::dispatch_lattice_with_options) 一样在网格上动态规划，
+    /// 边的概率和 [`decode`](Decoder::decode) 相同
+    fn decode_lattice(&self, lattice: &PinyinLattice, options: &ConvertOptions) -> Vec<Candidate> {
+        dispatch_lattice_with_edge(
+            &self.dag,
+            lattice,
+            options.path_num,
+            options.use_log_prob,
+            &options.decode,
+            |prev_item, phrase, dag_prob| {
+                self.edge_score(None, prev_item, phrase, dag_prob, options.min_prob)
+            },
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, &[]))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HybridDecoder, HybridWeights};
+    use crate::converter::Converter;
+    use crate::dag::tests::ShiZhangDag as TestDag;
+    use pinyinchch_type::hmm::Hmm;
+
+    /// "市" 之后是 "长" 的概率很大
+    struct TestHmm;
+
+    impl Hmm for TestHmm {
+        fn start(&self, _state: &str) -> f64 {
+            0.5
+        }
+
+        fn emission(&self, _state: &str, _observation: &str) -> f64 {
+            1.0
+        }
+
+        fn transition(&self, from_state: &str, to_state: &str) -> f64 {
+            match (from_state, to_state) {
+                ("市", "长") => 0.9,
+                _ => 0.1,
+            }
+        }
+
+        fn get_states(&self, _observation: &str) -> Vec<String> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn test_hybrid_decoder() {
+        // 只使用 DAG 时每个字取概率最大的
+        let converter = Converter::dag(TestDag).build();
+        assert_eq!(converter.convert("shizhang")[0].text(), "是张");
+        let only_dag = HybridWeights { dag: 1.0, hmm: 0.0 };
+        let decoder = HybridDecoder::new(TestDag, TestHmm).with_weights(only_dag);
+        let converter = Converter::builder(decoder).build();
+        assert_eq!(converter.convert("shizhang")[0].text(), "是张");
+
+        // 词组边界上的转移概率改变了结果
+        let converter = Converter::hybrid(TestDag, TestHmm).build();
+        let candidates = converter.convert("shizhang");
+        assert_eq!(candidates[0].text(), "市长");
+        assert_eq!(candidates[0].segments()[1].span(), 1..2);
+
+        let mut composition = converter.compose("shizhang");
+        assert_eq!(composition.candidates()[0].text(), "市长");
+        composition.select(&crate::converter::Segment::new("是", 0..1, 0.6));
+        assert_eq!(composition.candidates()[0].text(), "张");
+
+        // 在拼音网格上解码时边的概率相同
+        let lattice_converter = Converter::hybrid(TestDag, TestHmm)
+            .tokenizer(crate::converter::Tokenizer::Lattice)
+            .build();
+        let lattice_candidates = lattice_converter.convert("shizhang");
+        assert_eq!(lattice_candidates[0].text(), "市长");
+        assert_eq!(lattice_candidates[0].pinyins(), ["shi", "zhang"]);
+        assert_eq!(lattice_candidates[0].score(), candidates[0].score());
+    }
+}
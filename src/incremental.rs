@@ -0,0 +1,309 @@
+use crate::converter::{Candidate, ConvertOptions, Decoder};
+use crate::pinyin::{DEFAULT_SEPARATORS, tokenize_ranges};
+use std::ops::Range;
+
+/// 前缀树切分一个拼音时，从拼音的起点开始最多会查看的字节数
+///
+/// 最长的拼音是 6 个字母，以 g 结尾时还会查看后面的韵母，这里留出一些余量
+const MAX_LOOKAHEAD: usize = 12;
+
+/// 可以逐个拼音扩展的解码器，每个拼音对应动态规划中的一列
+pub trait IncrementalDecode: Decoder {
+    type Column;
+
+    /// 计算以 pinyin_seq 最后一个拼音结尾的列，columns 是之前每个拼音的列
+    fn extend(
+        &self,
+        columns: &[Self::Column],
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Self::Column;
+
+    /// 从所有的列得到按分数降序排列的候选结果，columns 至少有一列
+    fn finish(
+        &self,
+        columns: &[Self::Column],
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate>;
+}
+
+#[cfg(feature = "hmm")]
+impl<H: pinyinchch_type::hmm::Hmm> IncrementalDecode for crate::converter::HmmDecoder<H> {
+    type Column = crate::hmm::ViterbiColumn;
+
+    fn extend(
+        &self,
+        columns: &[Self::Column],
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Self::Column {
+        crate::hmm::viterbi_step(
+            &self.0,
+            columns.last(),
+            pinyin_seq[pinyin_seq.len() - 1],
+            options.path_num,
+            options.use_log_prob,
+            options.min_prob,
+            &options.decode,
+        )
+    }
+
+    fn finish(
+        &self,
+        columns: &[Self::Column],
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate> {
+        crate::hmm::viterbi_result(columns, options.path_num, String::clone)
+            .into_iter()
+            .map(|item| Candidate::from_item(item, pinyin_seq))
+            .collect()
+    }
+}
+
+#[cfg(feature = "dag")]
+impl<D: pinyinchch_type::dag::Dag> IncrementalDecode for crate::converter::DagDecoder<D> {
+    type Column = crate::priority::PrioritySet;
+
+    fn extend(
+        &self,
+        columns: &[Self::Column],
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Self::Column {
+        crate::dag::dispatch_step(
+            &self.0,
+            pinyin_seq,
+            columns,
+            options.path_num,
+            options.use_log_prob,
+            &options.decode,
+        )
+    }
+
+    fn finish(
+        &self,
+        columns: &[Self::Column],
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate> {
+        columns[columns.len() - 1]
+            .to_sorted_vec()
+            .into_iter()
+            .take(options.path_num)
+            .map(|item| Candidate::from_item(item, pinyin_seq))
+            .collect()
+    }
+}
+
+/// 增量解码器，适合输入法在每次按键之后重新转换
+///
+/// 保留每个拼音对应的动态规划列，追加或者删除拼音时只计算变化的部分。
+/// 输入整个拼音字符串时，只重新切分末尾不稳定的部分，切分方式和 [`Tokenizer::Trie`](crate::converter::Tokenizer::Trie) 一致
+pub struct IncrementalDecoder<D: IncrementalDecode> {
+    decoder: D,
+    options: ConvertOptions,
+    /// 当前的拼音字符串
+    input: String,
+    /// 每个拼音在 input 中的字节范围，直接追加的拼音没有范围
+    ranges: Vec<Option<Range<usize>>>,
+    pinyins: Vec<String>,
+    columns: Vec<D::Column>,
+}
+
+impl<D: IncrementalDecode> IncrementalDecoder<D> {
+    pub fn new(decoder: D, options: ConvertOptions) -> Self {
+        Self {
+            decoder,
+            options,
+            input: String::new(),
+            ranges: Vec::new(),
+            pinyins: Vec::new(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// 追加一个已经切分好的拼音
+    pub fn push(&mut self, pinyin: impl Into<String>) {
+        self.push_pinyin(pinyin.into(), None);
+    }
+
+    /// 删除最后一个拼音
+    pub fn pop(&mut self) -> Option<String> {
+        self.columns.pop();
+        if let Some(Some(range)) = self.ranges.pop() {
+            self.input.truncate(range.start);
+        }
+        self.pinyins.pop()
+    }
+
+    /// 设置整个拼音字符串，和之前的字符串相同的前缀中，不受后面字符影响的拼音会被保留
+    pub fn set_input(&mut self, input: impl AsRef<str>) {
+        let input = input.as_ref();
+        let common = self
+            .input
+            .char_indices()
+            .zip(input.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(
+                usize::min(self.input.len(), input.len()),
+                |((idx, _), _)| idx,
+            );
+
+        // 保留起点加上最大查看长度之后仍然在公共前缀中的拼音
+        let stable = self
+            .ranges
+            .iter()
+            .take_while(|range| {
+                range
+                    .as_ref()
+                    .is_some_and(|range| range.start + MAX_LOOKAHEAD <= common)
+            })
+            .count();
+        self.truncate(stable);
+
+        let offset = self
+            .ranges
+            .last()
+            .and_then(Option::as_ref)
+            .map_or(0, |range| range.end);
+        self.input = input.to_owned();
+        for range in tokenize_ranges(&input[offset..], DEFAULT_SEPARATORS) {
+            let range = range.start + offset..range.end + offset;
+            let pinyin = input[range.clone()].to_owned();
+            self.push_pinyin(pinyin, Some(range));
+        }
+    }
+
+    /// 在拼音字符串末尾追加字符
+    pub fn push_str(&mut self, text: &str) {
+        let input = format!("{}{text}", self.input);
+        self.set_input(input);
+    }
+
+    /// 删除拼音字符串的最后一个字符
+    pub fn backspace(&mut self) {
+        let mut input = self.input.clone();
+        input.pop();
+        self.set_input(input);
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+        self.input.clear();
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn pinyins(&self) -> &[String] {
+        &self.pinyins
+    }
+
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    pub fn options(&self) -> &ConvertOptions {
+        &self.options
+    }
+
+    /// 当前拼音序列的候选结果
+    pub fn candidates(&self) -> Vec<Candidate> {
+        if self.columns.is_empty() {
+            return Vec::new();
+        }
+        let pinyin_seq = self.pinyin_seq();
+        self.decoder
+            .finish(&self.columns, &pinyin_seq, &self.options)
+    }
+
+    fn push_pinyin(&mut self, pinyin: String, range: Option<Range<usize>>) {
+        self.pinyins.push(pinyin);
+        self.ranges.push(range);
+        let pinyin_seq = self.pinyin_seq();
+        let column = self
+            .decoder
+            .extend(&self.columns, &pinyin_seq, &self.options);
+        self.columns.push(column);
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.pinyins.truncate(len);
+        self.ranges.truncate(len);
+        self.columns.truncate(len);
+    }
+
+    fn pinyin_seq(&self) -> Vec<&str> {
+        self.pinyins.iter().map(String::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converter::{Converter, Tokenizer};
+    use pinyinchch_type::dag::Dag;
+
+    struct TestDag;
+
+    impl Dag for TestDag {
+        fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+            let phrases = match pinyin_list.join(",").as_str() {
+                "ni" => vec![("你".to_owned(), 0.8), ("泥".to_owned(), 0.2)],
+                "hao" => vec![("好".to_owned(), 0.7), ("号".to_owned(), 0.3)],
+                "ni,hao" => vec![("你好".to_owned(), 0.9)],
+                "xian" => vec![("先".to_owned(), 0.6)],
+                "xiang" => vec![("想".to_owned(), 0.7)],
+                "ni,xiang" => vec![("你想".to_owned(), 0.5)],
+                _ => Vec::new(),
+            };
+            phrases.into_iter().take(num).collect()
+        }
+    }
+
+    #[test]
+    fn test_incremental_decoder() {
+        let converter = Converter::dag(TestDag).path_num(3).build();
+        let mut decoder = Converter::dag(TestDag)
+            .path_num(3)
+            .build()
+            .into_incremental();
+        assert!(decoder.candidates().is_empty());
+
+        // 逐个按键输入，结果和每次重新转换一致
+        let input = "nihaonixiang";
+        for end in 1..=input.len() {
+            decoder.push_str(&input[end - 1..end]);
+            assert_eq!(decoder.input(), &input[..end]);
+            assert_eq!(decoder.candidates(), converter.convert(&input[..end]));
+        }
+        assert_eq!(decoder.pinyins(), ["ni", "hao", "ni", "xiang"]);
+
+        decoder.backspace();
+        assert_eq!(decoder.pinyins(), ["ni", "hao", "ni", "xian"]);
+        assert_eq!(decoder.candidates(), converter.convert("nihaonixian"));
+
+        assert_eq!(decoder.pop(), Some("xian".to_owned()));
+        assert_eq!(decoder.input(), "nihaoni");
+        decoder.push("hao");
+        assert_eq!(decoder.candidates()[0].text(), "你好你好");
+        decoder.clear();
+        assert!(decoder.pinyins().is_empty());
+        assert!(decoder.candidates().is_empty());
+    }
+
+    #[test]
+    fn test_incremental_decoder_tokenizer() {
+        // 增量解码使用前缀树切分，和 Tokenizer::Trie 一致
+        let converter = Converter::dag(TestDag).tokenizer(Tokenizer::Trie).build();
+        let mut decoder = Converter::dag(TestDag).build().into_incremental();
+        decoder.set_input("ni'hao");
+        assert_eq!(decoder.pinyins(), ["ni", "hao"]);
+        assert_eq!(decoder.candidates(), converter.convert("ni'hao"));
+        decoder.set_input("nixiang");
+        assert_eq!(decoder.pinyins(), ["ni", "xiang"]);
+        assert_eq!(decoder.candidates()[0].text(), "你想");
+    }
+}
@@ -0,0 +1,388 @@
+use crate::converter::Candidate;
+use crate::error::{DataError, InvalidLineSnafu, ReadFileSnafu, ReadLineSnafu, WriteFileSnafu};
+use snafu::{OptionExt, ResultExt, ensure};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// 每记录一次选择，之前的记录默认乘以的衰减系数
+pub const DEFAULT_DECAY: f64 = 0.98;
+
+/// 默认的学习速率，见 [`LearningHistory::weight`]
+pub const DEFAULT_LEARNING_RATE: f64 = 2.0;
+
+/// 可以在多个模型之间共享的学习记录，如 [`LearnedDag`](crate::dag::LearnedDag) 和 [`LearnedHmm`](crate::hmm::LearnedHmm)
+pub type SharedHistory = Arc<RwLock<LearningHistory>>;
+
+/// 一条记录的次数和最后一次更新时的序号
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Record {
+    count: f64,
+    tick: u64,
+}
+
+/// 用户选择的记录，记录 (拼音, 汉字) 和相邻汉字的次数，次数随着之后的选择逐渐衰减
+///
+/// 每次记录选择时序号加一，一条记录的次数是 `count * decay ^ (当前序号 - 记录的序号)`，
+/// 所以很久没有选择过的词语会逐渐被遗忘
+#[derive(Debug, Clone)]
+pub struct LearningHistory {
+    /// 以逗号连接的拼音和这个拼音下选择过的汉字
+    phrases: HashMap<String, HashMap<String, Record>>,
+    /// 前一个字和后一个字
+    transitions: HashMap<String, HashMap<String, Record>>,
+    tick: u64,
+    decay: f64,
+    rate: f64,
+}
+
+impl Default for LearningHistory {
+    fn default() -> Self {
+        Self {
+            phrases: HashMap::new(),
+            transitions: HashMap::new(),
+            tick: 0,
+            decay: DEFAULT_DECAY,
+            rate: DEFAULT_LEARNING_RATE,
+        }
+    }
+}
+
+impl LearningHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        let path = path.as_ref();
+        let file = File::open(path).context(ReadFileSnafu { path })?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// 读取 [`LearningHistory::write_to`] 写出的记录
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, DataError> {
+        let mut history = Self::new();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line.context(ReadLineSnafu { line: idx + 1 })?;
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let invalid = || InvalidLineSnafu {
+                line: idx + 1,
+                reason: format!("unexpected record {:?}", line.trim()),
+            };
+            match fields.as_slice() {
+                [] => {}
+                ["tick", tick] => history.tick = tick.parse().ok().with_context(invalid)?,
+                ["decay", decay] => history.decay = decay.parse().ok().with_context(invalid)?,
+                ["rate", rate] => history.rate = rate.parse().ok().with_context(invalid)?,
+                [kind @ ("phrase" | "transition"), key, value, count, tick] => {
+                    let record = Record {
+                        count: count.parse().ok().with_context(invalid)?,
+                        tick: tick.parse().ok().with_context(invalid)?,
+                    };
+                    ensure!(record.tick <= history.tick, invalid());
+                    let map = if *kind == "phrase" {
+                        &mut history.phrases
+                    } else {
+                        &mut history.transitions
+                    };
+                    let key = unescape(key).with_context(invalid)?;
+                    let value = unescape(value).with_context(invalid)?;
+                    map.entry(key).or_default().insert(value, record);
+                }
+                _ => return invalid().fail(),
+            }
+        }
+        Ok(history)
+    }
+
+    /// 设置衰减系数，取值范围是 (0, 1]，为 1 时不会遗忘
+    pub fn with_decay(mut self, decay: f64) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    /// 设置学习速率，速率越大，选择一次之后的提升越明显
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// 转换成可以共享的学习记录
+    pub fn into_shared(self) -> SharedHistory {
+        Arc::new(RwLock::new(self))
+    }
+
+    /// 记录一次选择，汉字的字数和拼音数量不一致时返回 false
+    ///
+    /// 除了整个词语，每个字的读音以及相邻的字也会被记录
+    pub fn record(&mut self, pinyins: &[&str], hanzi: &str) -> bool {
+        if pinyins.is_empty() || hanzi.chars().count() != pinyins.len() {
+            return false;
+        }
+        self.tick += 1;
+        self.learn_phrase(pinyins, hanzi);
+        self.learn_transitions(hanzi);
+        true
+    }
+
+    /// 记录用户选择的候选结果，每一段作为一个词语，整个结果只算作一次选择
+    pub fn record_candidate(&mut self, candidate: &Candidate) {
+        self.tick += 1;
+        let pinyins = candidate.pinyins();
+        for segment in candidate.segments() {
+            let Some(pinyin_seq) = pinyins.get(segment.span()) else {
+                continue;
+            };
+            let pinyin_seq = pinyin_seq.iter().map(String::as_str).collect::<Vec<_>>();
+            if segment.hanzi().chars().count() == pinyin_seq.len() {
+                self.learn_phrase(&pinyin_seq, segment.hanzi());
+            }
+        }
+        self.learn_transitions(candidate.text());
+    }
+
+    /// 衰减之后的次数
+    pub fn count(&self, pinyins: &[&str], hanzi: &str) -> f64 {
+        self.phrases
+            .get(&pinyins.join(","))
+            .and_then(|phrases| phrases.get(hanzi))
+            .map_or(0.0, |record| self.decayed(record))
+    }
+
+    /// 拼音下的汉字的学习权重，取值范围是 [0, 1)，没有记录时为 0
+    ///
+    /// 权重是 `1 - exp(-rate * count)`，次数越多越接近 1
+    pub fn weight(&self, pinyins: &[&str], hanzi: &str) -> f64 {
+        self.to_weight(self.count(pinyins, hanzi))
+    }
+
+    /// 前一个字之后是后一个字的学习权重
+    pub fn transition_weight(&self, from: &str, to: &str) -> f64 {
+        let count = self
+            .transitions
+            .get(from)
+            .and_then(|next| next.get(to))
+            .map_or(0.0, |record| self.decayed(record));
+        self.to_weight(count)
+    }
+
+    /// 拼音下选择过的所有汉字和它们的学习权重
+    pub fn phrases(&self, pinyins: &[&str]) -> Vec<(String, f64)> {
+        self.phrases
+            .get(&pinyins.join(","))
+            .into_iter()
+            .flatten()
+            .map(|(hanzi, record)| (hanzi.clone(), self.to_weight(self.decayed(record))))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty() && self.transitions.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.phrases.clear();
+        self.transitions.clear();
+        self.tick = 0;
+    }
+
+    /// 把学习记录保存到文件
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DataError> {
+        let path = path.as_ref();
+        let file = File::create(path).context(WriteFileSnafu { path })?;
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer)
+            .and_then(|_| writer.flush())
+            .context(WriteFileSnafu { path })
+    }
+
+    /// 写出学习记录，前三行是当前的序号、衰减系数和学习速率，之后每行是一条记录：
+    /// `phrase 拼音 汉字 次数 序号` 或者 `transition 前一个字 后一个字 次数 序号`
+    ///
+    /// 拼音和汉字中的 `%` 和空白字符按照 UTF-8 字节转义成 `%XX`，如空格是 `%20`
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "tick {}", self.tick)?;
+        writeln!(writer, "decay {}", self.decay)?;
+        writeln!(writer, "rate {}", self.rate)?;
+        for (kind, map) in [("phrase", &self.phrases), ("transition", &self.transitions)] {
+            let mut records = map
+                .iter()
+                .flat_map(|(key, values)| {
+                    values
+                        .iter()
+                        .map(move |(value, record)| (key, value, record))
+                })
+                .collect::<Vec<_>>();
+            records.sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+            for (key, value, record) in records {
+                writeln!(
+                    writer,
+                    "{kind} {} {} {} {}",
+                    escape(key),
+                    escape(value),
+                    record.count,
+                    record.tick
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 提升模型中的概率，提升之后的概率是 `prob + (1 - prob) * weight`
+    pub(crate) fn boost(prob: f64, weight: f64) -> f64 {
+        prob + (1.0 - prob) * weight
+    }
+
+    fn learn_phrase(&mut self, pinyins: &[&str], hanzi: &str) {
+        self.add(pinyins.join(","), hanzi.to_owned(), false);
+        if pinyins.len() > 1 {
+            for (pinyin, ch) in pinyins.iter().zip(hanzi.chars()) {
+                self.add(pinyin.to_string(), ch.to_string(), false);
+            }
+        }
+    }
+
+    fn learn_transitions(&mut self, text: &str) {
+        let chars = text.chars().collect::<Vec<_>>();
+        for pair in chars.windows(2) {
+            self.add(pair[0].to_string(), pair[1].to_string(), true);
+        }
+    }
+
+    fn add(&mut self, key: String, value: String, transition: bool) {
+        let map = if transition {
+            &mut self.transitions
+        } else {
+            &mut self.phrases
+        };
+        let (tick, decay) = (self.tick, self.decay);
+        let record = map
+            .entry(key)
+            .or_default()
+            .entry(value)
+            .or_insert(Record { count: 0.0, tick });
+        record.count = record.count * decay.powf((tick - record.tick) as f64) + 1.0;
+        record.tick = tick;
+    }
+
+    fn decayed(&self, record: &Record) -> f64 {
+        record.count * self.decay.powf((self.tick - record.tick) as f64)
+    }
+
+    fn to_weight(&self, count: f64) -> f64 {
+        1.0 - (-self.rate * count).exp()
+    }
+}
+
+/// 需要转义的字符，转义之后字段中不包含空白字符
+fn needs_escape(ch: char) -> bool {
+    ch == '%' || ch.is_whitespace()
+}
+
+/// 把 `%` 和空白字符转义成 `%XX`
+fn escape(field: &str) -> Cow<'_, str> {
+    if !field.contains(needs_escape) {
+        return Cow::Borrowed(field);
+    }
+    let mut escaped = String::with_capacity(field.len() + 8);
+    for ch in field.chars() {
+        if needs_escape(ch) {
+            for byte in ch.encode_utf8(&mut [0; 4]).bytes() {
+                escaped.push_str(&format!("%{byte:02X}"));
+            }
+        } else {
+            escaped.push(ch);
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// 还原 [`escape`] 转义的字段，格式不正确时返回 None
+fn unescape(field: &str) -> Option<String> {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = field.get(i + 1..i + 3)?;
+            unescaped.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            unescaped.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(unescaped).ok()
+}
+
+/// 读取共享的学习记录，锁被污染时仍然使用其中的数据
+pub(crate) fn read(history: &SharedHistory) -> std::sync::RwLockReadGuard<'_, LearningHistory> {
+    history.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// 修改共享的学习记录
+pub(crate) fn write(history: &SharedHistory) -> std::sync::RwLockWriteGuard<'_, LearningHistory> {
+    history.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LearningHistory;
+
+    #[test]
+    fn test_learning_history() {
+        let mut history = LearningHistory::new().with_decay(0.5);
+        assert!(history.record(&["ni", "hao"], "你好"));
+        assert!(!history.record(&["ni"], "你好"));
+        assert_eq!(history.count(&["ni", "hao"], "你好"), 1.0);
+        assert_eq!(history.count(&["hao"], "好"), 1.0);
+        assert!(history.transition_weight("你", "好") > 0.0);
+        assert_eq!(history.weight(&["ni"], "泥"), 0.0);
+
+        // 之后的每次选择都会让之前的记录衰减
+        history.record(&["ma"], "吗");
+        assert_eq!(history.count(&["ni", "hao"], "你好"), 0.5);
+        history.record(&["ni", "hao"], "你好");
+        assert_eq!(history.count(&["ni", "hao"], "你好"), 1.25);
+
+        let mut saved = Vec::new();
+        history.write_to(&mut saved).unwrap();
+        // 衰减系数和学习速率也会被保存
+        let loaded = LearningHistory::from_reader(saved.as_slice()).unwrap();
+        assert_eq!(loaded.count(&["ni", "hao"], "你好"), 1.25);
+        assert_eq!(loaded.count(&["ma"], "吗"), 0.5);
+        let mut resaved = Vec::new();
+        loaded.write_to(&mut resaved).unwrap();
+        assert_eq!(saved, resaved);
+
+        // 包含空白字符和 % 的字段会被转义
+        let mut history = LearningHistory::new().with_rate(1.5);
+        assert!(history.record(&["a"], " "));
+        assert!(history.record(&["a%"], "\u{3000}"));
+        let mut saved = Vec::new();
+        history.write_to(&mut saved).unwrap();
+        let text = String::from_utf8(saved.clone()).unwrap();
+        assert!(text.contains("phrase a %20 1 1\n"));
+        assert!(text.contains("phrase a%25 %E3%80%80 1 2\n"));
+        let loaded = LearningHistory::from_reader(saved.as_slice()).unwrap();
+        assert_eq!(loaded.count(&["a"], " "), history.count(&["a"], " "));
+        assert_eq!(
+            loaded.weight(&["a%"], "\u{3000}"),
+            history.weight(&["a%"], "\u{3000}")
+        );
+
+        // 只有相邻汉字的记录时也不为空
+        let history =
+            LearningHistory::from_reader("tick 1\ntransition 你 好 1 1\n".as_bytes()).unwrap();
+        assert!(!history.is_empty());
+
+        let err = LearningHistory::from_reader("tick 1\nphrase ni 你 1\n".as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 2 is invalid: unexpected record \"phrase ni 你 1\""
+        );
+    }
+}
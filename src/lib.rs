@@ -1,6 +1,20 @@
+#[cfg(any(feature = "hmm", feature = "dag"))]
+pub mod composition;
+#[cfg(any(feature = "hmm", feature = "dag"))]
+pub mod converter;
 #[cfg(feature = "dag")]
 pub mod dag;
+pub mod error;
 #[cfg(feature = "hmm")]
 pub mod hmm;
+#[cfg(all(feature = "hmm", feature = "dag"))]
+pub mod hybrid;
+#[cfg(any(feature = "hmm", feature = "dag"))]
+pub mod incremental;
+#[cfg(any(feature = "hmm", feature = "dag"))]
+pub mod learning;
+#[cfg(feature = "ngram")]
+pub mod ngram;
 pub mod pinyin;
+#[cfg(any(feature = "hmm", feature = "dag"))]
 mod priority;
@@ -0,0 +1,216 @@
+use crate::converter::{Candidate, ConvertOptions, Decoder};
+use crate::dag::{dispatch_lattice_with_edge, dispatch_with_edge};
+use crate::pinyin::PinyinLattice;
+use crate::priority::Item;
+use pinyinchch_type::dag::Dag;
+use pinyinchch_type::ngram::{LanguageModel, SENTENCE_START};
+
+/// DAG 词组概率的默认权重
+pub const DEFAULT_DAG_WEIGHT: f64 = 1.0;
+
+/// 在 DAG 的词组上使用词语级别语言模型的解码器
+///
+/// 和 [`dispatch`](crate::dag::dispatch) 一样在拼音上按照词组切分，每个词组的分数是
+/// `ln P(词组 | 前面的词组) + dag_weight * ln(DAG 中的词组概率)`，前面的词组最多使用语言模型的阶数减一个
+#[derive(Debug, Clone)]
+pub struct NgramDecoder<D, L> {
+    dag: D,
+    lm: L,
+    dag_weight: f64,
+}
+
+impl<D: Dag, L: LanguageModel> NgramDecoder<D, L> {
+    pub fn new(dag: D, lm: L) -> Self {
+        Self {
+            dag,
+            lm,
+            dag_weight: DEFAULT_DAG_WEIGHT,
+        }
+    }
+
+    /// 设置 DAG 词组概率的权重，为 0 时只使用语言模型
+    pub fn with_dag_weight(mut self, dag_weight: f64) -> Self {
+        self.dag_weight = dag_weight;
+        self
+    }
+
+    pub fn dag(&self) -> &D {
+        &self.dag
+    }
+
+    pub fn lm(&self) -> &L {
+        &self.lm
+    }
+
+    /// 使用动态规划算法解码，context 是第一个词组之前的词
+    fn dispatch(
+        &self,
+        context: &[&str],
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Item> {
+        dispatch_with_edge(
+            &self.dag,
+            pinyin_seq,
+            options.path_num,
+            options.use_log_prob,
+            &options.decode,
+            true,
+            |prev_item, phrase, dag_prob| {
+                self.edge_score(context, prev_item, phrase, dag_prob, options.min_prob)
+            },
+        )
+    }
+
+    /// 一条边的对数分数，前面的词组由 context 和 prev_item 的路径组成
+    fn edge_score(
+        &self,
+        context: &[&str],
+        prev_item: &Item,
+        phrase: &str,
+        dag_prob: f64,
+        min_prob: f64,
+    ) -> f64 {
+        let history_len = self.lm.order().saturating_sub(1);
+        let mut history = context.to_vec();
+        history.extend(prev_item.path().iter().map(String::as_str));
+        let history = &history[history.len().saturating_sub(history_len)..];
+        // 限制最小值，避免权重为 0 时得到 0 * 负无穷
+        let dag_prob = f64::max(dag_prob, min_prob);
+        self.lm.log_prob(history, phrase) + self.dag_weight * dag_prob.ln()
+    }
+}
+
+impl<D: Dag, L: LanguageModel> Decoder for NgramDecoder<D, L> {
+    /// 第一个词组的上文是句子开始的标记
+    fn decode(&self, pinyin_seq: &[&str], options: &ConvertOptions) -> Vec<Candidate> {
+        self.dispatch(&[SENTENCE_START], pinyin_seq, options)
+            .into_iter()
+            .map(|item| Candidate::from_item(item, pinyin_seq))
+            .collect()
+    }
+
+    /// 使用 context 这个词组作为第一个词组的上文，context 为空时和 [`decode`](Self::decode) 一样使用句子开始的标记
+    fn decode_with_context(
+        &self,
+        context: &str,
+        pinyin_seq: &[&str],
+        options: &ConvertOptions,
+    ) -> Vec<Candidate> {
+        if context.is_empty() {
+            return self.decode(pinyin_seq, options);
+        }
+        self.dispatch(&[context], pinyin_seq, options)
+            .into_iter()
+            .map(|item| Candidate::from_item(item, pinyin_seq))
+            .collect()
+    }
+
+    /// 和 [`dispatch_lattice_with_options`](crate::dag::dispatch_lattice_with_options) 一样在网格上动态规划，
+    /// 第一个词组的上文是句子开始的标记
+    fn decode_lattice(&self, lattice: &PinyinLattice, options: &ConvertOptions) -> Vec<Candidate> {
+        dispatch_lattice_with_edge(
+            &self.dag,
+            lattice,
+            options.path_num,
+            options.use_log_prob,
+            &options.decode,
+            |prev_item, phrase, dag_prob| {
+                self.edge_score(
+                    &[SENTENCE_START],
+                    prev_item,
+                    phrase,
+                    dag_prob,
+                    options.min_prob,
+                )
+            },
+        )
+        .into_iter()
+        .map(|item| Candidate::from_item(item, &[]))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NgramDecoder;
+    use crate::converter::Converter;
+    use crate::dag::tests::ShiZhangDag;
+    use pinyinchch_type::dag::Dag;
+    use pinyinchch_type::ngram::LanguageModel;
+
+    /// 在 [`ShiZhangDag`] 之上添加 "市长"、"讲话" 和 "江华" 三个词组
+    struct TestDag;
+
+    impl Dag for TestDag {
+        fn get_phrase(&self, pinyin_list: &[&str], num: usize) -> Vec<(String, f64)> {
+            let phrases = match pinyin_list.join(",").as_str() {
+                "shi,zhang" => vec![("市长".to_owned(), 0.9)],
+                "jiang,hua" => vec![("讲话".to_owned(), 0.7), ("江华".to_owned(), 0.3)],
+                _ => return ShiZhangDag.get_phrase(pinyin_list, num),
+            };
+            phrases.into_iter().take(num).collect()
+        }
+    }
+
+    /// "市长" 之后是 "讲话" 的概率很大，其它情况下 "江华" 更常见
+    struct TestLm;
+
+    impl LanguageModel for TestLm {
+        fn order(&self) -> usize {
+            2
+        }
+
+        fn log_prob(&self, context: &[&str], word: &str) -> f64 {
+            let prob = match (context.last().copied(), word) {
+                (Some("市长"), "讲话") => 0.8,
+                (_, "讲话") => 0.01,
+                (_, "江华") => 0.05,
+                _ => 0.1,
+            };
+            f64::ln(prob)
+        }
+    }
+
+    #[test]
+    fn test_ngram_decoder() {
+        let converter = Converter::dag(TestDag).build();
+        assert_eq!(converter.convert("jianghua")[0].text(), "讲话");
+
+        // 词组之间的上文改变了结果
+        let converter = Converter::ngram(TestDag, TestLm).build();
+        assert_eq!(converter.convert("jianghua")[0].text(), "江华");
+        let candidates = converter.convert("shizhangjianghua");
+        assert_eq!(candidates[0].text(), "市长讲话");
+        assert_eq!(candidates[0].segments()[1].span(), 2..4);
+        // 段落中记录的是 DAG 中的词组概率
+        assert_eq!(candidates[0].segments()[1].prob(), 0.7);
+
+        // 在拼音网格上解码时边的分数相同
+        let lattice_converter = Converter::ngram(TestDag, TestLm)
+            .tokenizer(crate::converter::Tokenizer::Lattice)
+            .build();
+        let lattice_candidates = lattice_converter.convert("shizhangjianghua");
+        assert_eq!(lattice_candidates[0].text(), "市长讲话");
+        assert_eq!(
+            lattice_candidates[0].pinyins(),
+            ["shi", "zhang", "jiang", "hua"]
+        );
+        assert_eq!(lattice_candidates[0].score(), candidates[0].score());
+
+        // 只使用语言模型
+        let decoder = NgramDecoder::new(TestDag, TestLm).with_dag_weight(0.0);
+        let converter = Converter::builder(decoder).build();
+        assert_eq!(converter.convert("jianghua")[0].text(), "江华");
+
+        // 选择的词组作为之后的上文
+        let converter = Converter::ngram(TestDag, TestLm).build();
+        let mut composition = converter.compose("shizhangjianghua");
+        assert!(composition.select(&crate::converter::Segment::new("市长", 0..2, 0.9)));
+        assert_eq!(composition.candidates()[0].text(), "讲话");
+        composition.undo();
+        assert!(composition.select(&crate::converter::Segment::new("市", 0..1, 0.4)));
+        assert!(composition.select(&crate::converter::Segment::new("长", 0..1, 0.4)));
+        assert_eq!(composition.candidates()[0].text(), "江华");
+    }
+}
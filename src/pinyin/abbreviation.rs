@@ -0,0 +1,123 @@
+use crate::pinyin::trie_tokenizer::PINYIN_TRIE;
+use crate::pinyin::{DEFAULT_SEPARATORS, INITIALS, VALID_PINYIN, split_initial, to_plain};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// 声母和以这个声母开头的所有拼音的映射，拼音按照字母顺序排列
+static SYLLABLES_BY_INITIAL: LazyLock<HashMap<&'static str, Vec<&'static str>>> =
+    LazyLock::new(|| {
+        let mut syllables = HashMap::<&'static str, Vec<&'static str>>::new();
+        for pinyin in VALID_PINYIN.iter() {
+            let (initial, _) = split_initial(pinyin);
+            if !initial.is_empty() {
+                syllables.entry(initial).or_default().push(pinyin);
+            }
+        }
+        for list in syllables.values_mut() {
+            list.sort_unstable();
+        }
+        syllables
+    });
+
+/// 简拼中的一个音节，可以是完整的拼音，也可以只是声母
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbbreviatedPinyin {
+    /// 完整的拼音，如 bei
+    Full(&'static str),
+    /// 只有声母，如 j、zh
+    Initial(&'static str),
+}
+
+impl AbbreviatedPinyin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AbbreviatedPinyin::Full(pinyin) | AbbreviatedPinyin::Initial(pinyin) => pinyin,
+        }
+    }
+
+    pub fn is_initial(&self) -> bool {
+        matches!(self, AbbreviatedPinyin::Initial(_))
+    }
+
+    /// 可能的完整拼音，完整的拼音只有它自己，声母展开成所有以它开头的拼音
+    ///
+    /// 如 z => [za, zai, ...]，不包含 zh 开头的拼音
+    pub fn expand(&self) -> Vec<&'static str> {
+        match self {
+            AbbreviatedPinyin::Full(pinyin) => vec![*pinyin],
+            AbbreviatedPinyin::Initial(initial) => SYLLABLES_BY_INITIAL
+                .get(initial)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// 切分简拼，支持完整拼音和声母混合输入
+///
+/// 优先匹配完整的拼音，否则匹配一个声母（zh、ch 和 sh 优先于单字母），其它字符会被跳过，
+/// [`DEFAULT_SEPARATORS`] 中的字符是强制的切分位置
+///
+/// 如 bj => [b, j]；beij => [bei, j]；zhrmgh => [zh, r, m, g, h]
+pub fn pinyin_tokenize_abbreviated(value: impl AsRef<str>) -> Vec<AbbreviatedPinyin> {
+    let value = to_plain(value.as_ref());
+    let mut pinyins = Vec::new();
+    let len = value.len();
+    let mut i = 0;
+    while i < len {
+        let temp = &value[i..];
+        let (buf, is_success) = PINYIN_TRIE.find(temp, DEFAULT_SEPARATORS);
+        if is_success && let Some(pinyin) = VALID_PINYIN.get(buf.as_str()) {
+            pinyins.push(AbbreviatedPinyin::Full(pinyin));
+            i += pinyin.len();
+        } else if let Some(initial) = INITIALS.iter().find(|initial| temp.starts_with(*initial)) {
+            pinyins.push(AbbreviatedPinyin::Initial(initial));
+            i += initial.len();
+        } else {
+            i += temp.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    pinyins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AbbreviatedPinyin::{Full, Initial};
+    use super::pinyin_tokenize_abbreviated;
+
+    #[test]
+    fn test_pinyin_tokenize_abbreviated() {
+        assert_eq!(
+            pinyin_tokenize_abbreviated("bj"),
+            [Initial("b"), Initial("j")]
+        );
+        assert_eq!(
+            pinyin_tokenize_abbreviated("beij"),
+            [Full("bei"), Initial("j")]
+        );
+        assert_eq!(
+            pinyin_tokenize_abbreviated("zhrmgh"),
+            [
+                Initial("zh"),
+                Initial("r"),
+                Initial("m"),
+                Initial("g"),
+                Initial("h")
+            ]
+        );
+        assert_eq!(
+            pinyin_tokenize_abbreviated("nihao"),
+            [Full("ni"), Full("hao")]
+        );
+        assert_eq!(
+            pinyin_tokenize_abbreviated("x'an"),
+            [Initial("x"), Full("an")]
+        );
+
+        let expanded = Initial("z").expand();
+        assert!(expanded.contains(&"zi"));
+        assert!(!expanded.contains(&"zhi"));
+        assert!(Initial("zh").expand().contains(&"zhong"));
+        assert_eq!(Full("bei").expand(), ["bei"]);
+    }
+}
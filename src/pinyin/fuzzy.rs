@@ -0,0 +1,279 @@
+use crate::pinyin::trie_tokenizer::{PINYIN_TRIE, TrieNode};
+use crate::pinyin::{DEFAULT_SEPARATORS, VALID_PINYIN, split_initial, tokenize_ranges_with};
+use std::collections::HashSet;
+
+/// 没有设置惩罚系数时，每一次模糊音替换使用的惩罚系数
+pub const DEFAULT_FUZZY_PENALTY: f64 = 0.5;
+
+/// 一条模糊音规则，规则是双向的，如 z/zh 表示 z 和 zh 可以互相替换
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FuzzyRule {
+    /// z <=> zh
+    ZZh,
+    /// c <=> ch
+    CCh,
+    /// s <=> sh
+    SSh,
+    /// n <=> l
+    NL,
+    /// f <=> h
+    FH,
+    /// an <=> ang，包括 ian <=> iang、uan <=> uang
+    AnAng,
+    /// en <=> eng
+    EnEng,
+    /// in <=> ing
+    InIng,
+}
+
+impl FuzzyRule {
+    /// 所有的模糊音规则
+    pub const ALL: [FuzzyRule; 8] = [
+        FuzzyRule::ZZh,
+        FuzzyRule::CCh,
+        FuzzyRule::SSh,
+        FuzzyRule::NL,
+        FuzzyRule::FH,
+        FuzzyRule::AnAng,
+        FuzzyRule::EnEng,
+        FuzzyRule::InIng,
+    ];
+
+    /// 声母规则返回 Some((a, b))
+    fn initial_pair(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            FuzzyRule::ZZh => Some(("z", "zh")),
+            FuzzyRule::CCh => Some(("c", "ch")),
+            FuzzyRule::SSh => Some(("s", "sh")),
+            FuzzyRule::NL => Some(("n", "l")),
+            FuzzyRule::FH => Some(("f", "h")),
+            _ => None,
+        }
+    }
+
+    /// 韵母规则返回 Some((前鼻音, 后鼻音))
+    fn final_pair(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            FuzzyRule::AnAng => Some(("an", "ang")),
+            FuzzyRule::EnEng => Some(("en", "eng")),
+            FuzzyRule::InIng => Some(("in", "ing")),
+            _ => None,
+        }
+    }
+}
+
+/// 一组模糊音规则，用于把一个拼音展开成多个有效的拼音
+///
+/// 每一次替换（声母或者韵母）都会把概率乘以惩罚系数，所以同时替换了声母和韵母的拼音的权重是 penalty²
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyRules {
+    rules: Vec<FuzzyRule>,
+    penalty: f64,
+}
+
+impl Default for FuzzyRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FuzzyRules {
+    /// 不包含任何规则
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            penalty: DEFAULT_FUZZY_PENALTY,
+        }
+    }
+
+    /// 包含所有的规则
+    pub fn all() -> Self {
+        Self::new().with_rules(FuzzyRule::ALL)
+    }
+
+    pub fn with_rule(mut self, rule: FuzzyRule) -> Self {
+        if !self.rules.contains(&rule) {
+            self.rules.push(rule);
+        }
+        self
+    }
+
+    pub fn with_rules(self, rules: impl IntoIterator<Item = FuzzyRule>) -> Self {
+        rules.into_iter().fold(self, Self::with_rule)
+    }
+
+    /// 设置每一次替换的惩罚系数，取值范围是 (0, 1]
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    pub fn contains(&self, rule: FuzzyRule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    pub fn rules(&self) -> &[FuzzyRule] {
+        &self.rules
+    }
+
+    pub fn penalty(&self) -> f64 {
+        self.penalty
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 把一个拼音展开成它的所有模糊音，返回 (拼音, 权重)
+    ///
+    /// 第一个总是原来的拼音，权重为 1.0，只会生成有效的拼音，如 zan => [(zan, 1.0), (zang, 0.5), (zhan, 0.5), (zhang, 0.25)]
+    pub fn expand(&self, pinyin: &str) -> Vec<(String, f64)> {
+        let mut expanded = vec![(pinyin.to_owned(), 1.0)];
+        let (initial, rest) = split_initial(pinyin);
+        let initials = self.initial_variants(initial);
+        let finals = self.final_variants(rest);
+        for (initial_cost, initial) in initials.iter().enumerate() {
+            for (final_cost, rest) in finals.iter().enumerate() {
+                let cost = i32::from(initial_cost > 0) + i32::from(final_cost > 0);
+                if cost == 0 {
+                    continue;
+                }
+                let candidate = format!("{initial}{rest}");
+                if VALID_PINYIN.contains(candidate.as_str())
+                    && !expanded.iter().any(|(pinyin, _)| *pinyin == candidate)
+                {
+                    expanded.push((candidate, self.penalty.powi(cost)));
+                }
+            }
+        }
+        expanded
+    }
+
+    /// 声母和按照规则替换之后的声母，第一个是原来的声母
+    fn initial_variants<'a>(&self, initial: &'a str) -> Vec<&'a str> {
+        let mut variants = vec![initial];
+        for (a, b) in self.rules.iter().filter_map(FuzzyRule::initial_pair) {
+            if initial == a {
+                variants.push(b);
+            } else if initial == b {
+                variants.push(a);
+            }
+        }
+        variants
+    }
+
+    /// 韵母和按照规则替换之后的韵母，第一个是原来的韵母
+    fn final_variants(&self, rest: &str) -> Vec<String> {
+        let mut variants = vec![rest.to_owned()];
+        for (front, back) in self.rules.iter().filter_map(FuzzyRule::final_pair) {
+            if let Some(head) = rest.strip_suffix(back) {
+                variants.push(format!("{head}{front}"));
+            } else if let Some(head) = rest.strip_suffix(front) {
+                variants.push(format!("{head}{back}"));
+            }
+        }
+        variants
+    }
+}
+
+/// 支持模糊音的拼音切分器，除了有效的拼音，还会切分出按照规则替换声母或者韵母之后得到的拼音，
+/// 如 f/h 规则下的 fong（hong 的模糊音）。切分出的拼音由 [`FuzzyHmm`](crate::hmm::FuzzyHmm)
+/// 或者 [`FuzzyDag`](crate::dag::FuzzyDag) 按照相同的规则展开成有效的拼音
+#[derive(Debug, Clone)]
+pub struct FuzzyTokenizer {
+    trie: TrieNode<'static>,
+}
+
+impl FuzzyTokenizer {
+    pub fn new(rules: &FuzzyRules) -> Self {
+        let mut trie = PINYIN_TRIE.clone();
+        let syllables = PINYIN_TRIE.syllables();
+        // 韵母都来自前缀树，替换之后的韵母也需要是前缀树中已有的韵母
+        let finals = syllables
+            .iter()
+            .map(|(_, rest)| *rest)
+            .collect::<HashSet<_>>();
+        for (initial, rest) in syllables {
+            if initial.is_empty() {
+                continue;
+            }
+            for variant_initial in rules.initial_variants(initial) {
+                for variant_rest in rules.final_variants(rest) {
+                    if let Some(variant_rest) = finals.get(variant_rest.as_str()) {
+                        trie.add(&[variant_initial, variant_rest]);
+                    }
+                }
+            }
+        }
+        Self { trie }
+    }
+
+    /// 和 [`pinyin_tokenize`](crate::pinyin::pinyin_tokenize) 相同，使用 [`DEFAULT_SEPARATORS`] 作为分隔符
+    pub fn tokenize(&self, value: impl AsRef<str>) -> Vec<String> {
+        let value = value.as_ref();
+        tokenize_ranges_with(&self.trie, value, DEFAULT_SEPARATORS)
+            .into_iter()
+            .map(|range| value[range].to_owned())
+            .collect()
+    }
+}
+
+/// 使用模糊音规则切分拼音，如 f/h 规则下 fonghua => [fong, hua]，见 [`FuzzyTokenizer`]
+///
+/// 每次调用都会重新构建前缀树，多次切分时应该使用 [`FuzzyTokenizer`]
+pub fn pinyin_tokenize_fuzzy(value: impl AsRef<str>, rules: &FuzzyRules) -> Vec<String> {
+    FuzzyTokenizer::new(rules).tokenize(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FuzzyRule, FuzzyRules, FuzzyTokenizer, pinyin_tokenize_fuzzy};
+    use crate::pinyin::pinyin_tokenize;
+
+    #[test]
+    fn test_fuzzy_expand() {
+        let rules = FuzzyRules::all();
+        let mut expanded = rules.expand("zan");
+        assert_eq!(expanded[0], ("zan".to_owned(), 1.0));
+        expanded.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            expanded,
+            [
+                ("zan".to_owned(), 1.0),
+                ("zang".to_owned(), 0.5),
+                ("zhan".to_owned(), 0.5),
+                ("zhang".to_owned(), 0.25),
+            ]
+        );
+        // 只生成有效的拼音，lv 只有 nv 一个模糊音
+        let expanded = FuzzyRules::new().with_rule(FuzzyRule::NL).expand("lv");
+        assert_eq!(expanded, [("lv".to_owned(), 1.0), ("nv".to_owned(), 0.5)]);
+        let expanded = FuzzyRules::all().with_penalty(0.8).expand("xing");
+        assert_eq!(
+            expanded,
+            [("xing".to_owned(), 1.0), ("xin".to_owned(), 0.8)]
+        );
+        // 零声母的拼音
+        let expanded = FuzzyRules::all().expand("en");
+        assert_eq!(expanded, [("en".to_owned(), 1.0), ("eng".to_owned(), 0.5)]);
+        // 没有规则时只有原来的拼音
+        let expanded = FuzzyRules::new().expand("zhi");
+        assert_eq!(expanded, [("zhi".to_owned(), 1.0)]);
+    }
+
+    #[test]
+    fn test_fuzzy_tokenizer() {
+        // fong 不是有效的拼音，f/h 规则下是 hong 的模糊音
+        let rules = FuzzyRules::new().with_rule(FuzzyRule::FH);
+        assert_eq!(pinyin_tokenize_fuzzy("fonghua", &rules), ["fong", "hua"]);
+        assert_eq!(pinyin_tokenize("fonghua"), ["fo", "ng", "hua"]);
+        // 替换韵母，biang 是 bian 的模糊音
+        let rules = FuzzyRules::new().with_rule(FuzzyRule::AnAng);
+        assert_eq!(pinyin_tokenize_fuzzy("biang", &rules), ["biang"]);
+        assert_eq!(pinyin_tokenize("biang"), ["bian"]);
+        // 有效的拼音和 pinyin_tokenize 一致
+        let tokenizer = FuzzyTokenizer::new(&FuzzyRules::all());
+        assert_eq!(tokenizer.tokenize("xi'an"), ["xi", "an"]);
+        assert_eq!(tokenizer.tokenize("zhongguo"), pinyin_tokenize("zhongguo"));
+    }
+}
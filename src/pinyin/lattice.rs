@@ -0,0 +1,211 @@
+use crate::pinyin::{DEFAULT_SEPARATORS, VALID_PINYIN, to_plain};
+
+/// 最长的拼音是 6 个字母，如 zhuang
+const MAX_PINYIN_LEN: usize = 6;
+
+/// 拼音网格，包含一个拼音字符串所有有效的切分方式
+///
+/// 节点是切分的位置，边是一个有效的拼音。只保留能从起点走到终点的边，
+/// 所以从第一个节点到最后一个节点的每一条路径都是一种切分方式，
+/// 如 xian 的网格中有 xian 和 xi an 两条路径
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PinyinLattice {
+    /// 每个节点在去除声调和分隔符之后的字符串中的字节位置
+    positions: Vec<usize>,
+    /// 每个节点出发的边，(终点节点, 拼音)
+    edges: Vec<Vec<(usize, String)>>,
+}
+
+impl PinyinLattice {
+    /// 节点的数量，没有有效的切分时为 0
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// 从 node 出发的边，(终点节点, 拼音)，终点节点总是大于 node
+    pub fn edges(&self, node: usize) -> &[(usize, String)] {
+        &self.edges[node]
+    }
+
+    /// 节点在去除声调和分隔符之后的字符串中的字节位置
+    pub fn position(&self, node: usize) -> usize {
+        self.positions[node]
+    }
+
+    /// 列出所有的切分方式，结果和 [`pinyin_split`](crate::pinyin::pinyin_split) 一致
+    ///
+    /// 切分方式的数量随拼音长度指数增长，只需要前几种时使用 [`iter_segmentations`](Self::iter_segmentations)
+    pub fn segmentations(&self) -> Vec<Vec<&str>> {
+        self.iter_segmentations().collect()
+    }
+
+    /// 逐个生成切分方式，每个位置先尝试更长的拼音，所以第一个结果是最长匹配的切分
+    pub fn iter_segmentations(&self) -> impl Iterator<Item = Vec<&str>> + '_ {
+        // (节点, 已经尝试过的边的数量)，solution 是从起点到栈顶节点经过的拼音
+        let mut stack = if self.is_empty() {
+            Vec::new()
+        } else {
+            vec![(0, 0)]
+        };
+        let mut solution = Vec::new();
+        std::iter::from_fn(move || {
+            while let Some(&(node, tried)) = stack.last() {
+                let edges = &self.edges[node];
+                if node == self.len() - 1 || tried == edges.len() {
+                    let found = (node == self.len() - 1).then(|| solution.clone());
+                    stack.pop();
+                    solution.pop();
+                    if found.is_some() {
+                        return found;
+                    }
+                    continue;
+                }
+                let (to, pinyin) = &edges[edges.len() - 1 - tried];
+                stack.last_mut().unwrap().1 += 1;
+                solution.push(pinyin.as_str());
+                stack.push((*to, 0));
+            }
+            None
+        })
+    }
+
+    /// 从 node 出发，最多经过 max_len 条边的所有路径，返回 (终点节点, 拼音列表)
+    pub fn paths_from(&self, node: usize, max_len: usize) -> Vec<(usize, Vec<&str>)> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(node, Vec::new())];
+        while let Some((cur, pinyins)) = stack.pop() {
+            if pinyins.len() == max_len {
+                continue;
+            }
+            for (to, pinyin) in &self.edges[cur] {
+                let mut next = pinyins.clone();
+                next.push(pinyin.as_str());
+                paths.push((*to, next.clone()));
+                stack.push((*to, next));
+            }
+        }
+        paths
+    }
+}
+
+/// 构建拼音字符串的拼音网格，字符串必须全部由有效的拼音组成，否则返回空的网格
+///
+/// 如 xian => 0 -xian-> 2、0 -xi-> 1 -an-> 2，使用 [`DEFAULT_SEPARATORS`] 作为分隔符
+pub fn pinyin_lattice(value: impl AsRef<str>) -> PinyinLattice {
+    pinyin_lattice_with_separators(value, DEFAULT_SEPARATORS)
+}
+
+/// 构建拼音网格，separators 中的字符是强制的切分位置，没有边会跨过分隔符
+///
+/// 如 xi'an => 0 -xi-> 1 -an-> 2
+pub fn pinyin_lattice_with_separators(
+    value: impl AsRef<str>,
+    separators: &[char],
+) -> PinyinLattice {
+    let mut pinyin = String::new();
+    // boundary[i] 表示位置 i 是强制的切分位置
+    let mut boundary = Vec::new();
+    for part in value.as_ref().split(separators) {
+        pinyin.push_str(&to_plain(part));
+        boundary.resize(pinyin.len(), false);
+        boundary.push(true);
+    }
+    if pinyin.is_empty() || !pinyin.is_ascii() {
+        return PinyinLattice::default();
+    }
+    let len = pinyin.len();
+
+    // complete[i] 表示从 i 开始的子串存在有效的切分
+    let mut complete = vec![false; len + 1];
+    complete[len] = true;
+    let mut edges_at = vec![Vec::new(); len + 1];
+    for start in (0..len).rev() {
+        for end in start + 1..=usize::min(len, start + MAX_PINYIN_LEN) {
+            // 边不能跨过分隔符
+            if boundary[start + 1..end].contains(&true) {
+                break;
+            }
+            let slice = &pinyin[start..end];
+            if complete[end] && VALID_PINYIN.contains(slice) {
+                complete[start] = true;
+                edges_at[start].push((end, slice.to_owned()));
+            }
+        }
+    }
+    if !complete[0] {
+        return PinyinLattice::default();
+    }
+
+    // 只保留从起点可以到达的位置
+    let mut reachable = vec![false; len + 1];
+    reachable[0] = true;
+    for start in 0..len {
+        if reachable[start] {
+            for (end, _) in &edges_at[start] {
+                reachable[*end] = true;
+            }
+        }
+    }
+    let positions = (0..=len).filter(|pos| reachable[*pos]).collect::<Vec<_>>();
+    let mut node_of = vec![usize::MAX; len + 1];
+    for (node, pos) in positions.iter().enumerate() {
+        node_of[*pos] = node;
+    }
+    let edges = positions
+        .iter()
+        .map(|pos| {
+            edges_at[*pos]
+                .iter()
+                .map(|(end, pinyin)| (node_of[*end], pinyin.clone()))
+                .collect()
+        })
+        .collect();
+
+    PinyinLattice { positions, edges }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pinyin_lattice;
+
+    #[test]
+    fn test_pinyin_lattice() {
+        let lattice = pinyin_lattice("xian");
+        assert_eq!(lattice.len(), 3);
+        let mut segmentations = lattice.segmentations();
+        segmentations.sort();
+        assert_eq!(segmentations, [vec!["xi", "an"], vec!["xian"]]);
+        assert_eq!(lattice.paths_from(0, 1).len(), 2);
+        assert_eq!(lattice.iter_segmentations().next(), Some(vec!["xian"]));
+
+        let lattice = pinyin_lattice("jínan");
+        let mut segmentations = lattice.segmentations();
+        segmentations.sort();
+        assert_eq!(segmentations, [vec!["ji", "nan"], vec!["jin", "an"]]);
+
+        assert!(pinyin_lattice("zhangssan").is_empty());
+        assert!(pinyin_lattice("").is_empty());
+
+        let lattice = pinyin_lattice("xi'an");
+        assert_eq!(lattice.segmentations(), [vec!["xi", "an"]]);
+        assert_eq!(lattice.position(1), 2);
+        let lattice = pinyin_lattice("xian-jin'an");
+        let mut segmentations = lattice.segmentations();
+        segmentations.sort();
+        assert_eq!(
+            segmentations,
+            [vec!["xi", "an", "jin", "an"], vec!["xian", "jin", "an"]]
+        );
+        assert_eq!(
+            lattice.iter_segmentations().take(1).collect::<Vec<_>>(),
+            [vec!["xian", "jin", "an"]]
+        );
+        let lattice = super::pinyin_lattice_with_separators("xi/an", &['/']);
+        assert_eq!(lattice.segmentations(), [vec!["xi", "an"]]);
+        assert!(pinyin_lattice("zhan'gssan").is_empty());
+    }
+}
@@ -1,7 +1,30 @@
+mod abbreviation;
+mod fuzzy;
+mod lattice;
+mod paragraph;
+mod span;
+mod token;
+mod tone;
 mod trie_tokenizer;
 
-use crate::pinyin::trie_tokenizer::PINYIN_TRIE;
+pub use crate::pinyin::abbreviation::{AbbreviatedPinyin, pinyin_tokenize_abbreviated};
+pub use crate::pinyin::fuzzy::{
+    DEFAULT_FUZZY_PENALTY, FuzzyRule, FuzzyRules, FuzzyTokenizer, pinyin_tokenize_fuzzy,
+};
+pub use crate::pinyin::lattice::{PinyinLattice, pinyin_lattice, pinyin_lattice_with_separators};
+pub use crate::pinyin::paragraph::{
+    Chunk, FullWidthPunct, split_paragraph, split_paragraph_with_policy,
+};
+pub use crate::pinyin::span::{PinyinSpan, pinyin_split_spans, pinyin_tokenize_spans};
+pub use crate::pinyin::token::{AsciiPolicy, Token, pinyin_tokenize_mixed};
+#[cfg(any(feature = "hmm", feature = "dag"))]
+pub(crate) use crate::pinyin::tone::plain_lowercase;
+pub use crate::pinyin::tone::{
+    DEFAULT_TONE_PENALTY, PinyinStyle, Tone, ToneTable, TonedPinyin, pinyin_tokenize_toned,
+};
+use crate::pinyin::trie_tokenizer::{PINYIN_TRIE, TrieNode};
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::LazyLock;
 
 /// 带声调的韵母和和不带声调的韵母的映射
@@ -82,23 +105,57 @@ static VALID_PINYIN: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
     .collect()
 });
 
-/// 借助前缀树构建的 tokenizer 来切分拼音
+/// 所有的声母，zh、ch 和 sh 需要在单字母之前匹配
+const INITIALS: [&str; 23] = [
+    "zh", "ch", "sh", "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "h", "j", "q", "x", "r",
+    "z", "c", "s", "y", "w",
+];
+
+/// 默认的拼音分隔符，分隔符两边的字母不会被切分到同一个拼音中，如 xi'an => ["xi", "an"]
+pub const DEFAULT_SEPARATORS: &[char] = &['\'', '’', '-'];
+
+/// 借助前缀树构建的 tokenizer 来切分拼音，使用 [`DEFAULT_SEPARATORS`] 作为分隔符
 pub fn pinyin_tokenize(value: impl AsRef<str>) -> Vec<String> {
+    pinyin_tokenize_with_separators(value, DEFAULT_SEPARATORS)
+}
+
+/// 借助前缀树构建的 tokenizer 来切分拼音，拼音不会跨过 separators 中的字符
+pub fn pinyin_tokenize_with_separators(value: impl AsRef<str>, separators: &[char]) -> Vec<String> {
     let value = value.as_ref();
-    let mut pinyins = Vec::<String>::new();
+    tokenize_ranges(value, separators)
+        .into_iter()
+        .map(|range| value[range].to_owned())
+        .collect()
+}
+
+/// 借助前缀树切分拼音，返回每个拼音在 value 中的字节范围
+///
+/// 从某个位置开始的切分结果只取决于这个位置之后的字符串
+pub(crate) fn tokenize_ranges(value: &str, separators: &[char]) -> Vec<Range<usize>> {
+    tokenize_ranges_with(&PINYIN_TRIE, value, separators)
+}
+
+/// 和 [`tokenize_ranges`] 相同，但是使用 trie 切分
+pub(crate) fn tokenize_ranges_with(
+    trie: &TrieNode,
+    value: &str,
+    separators: &[char],
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
     let len = value.len();
     let mut i = 0;
     while i < len {
         let temp = &value[i..];
-        let (buf, is_success) = PINYIN_TRIE.find(temp);
+        let (buf, is_success) = trie.find(temp, separators);
         if is_success && !buf.is_empty() {
-            pinyins.push(buf.to_owned());
+            ranges.push(i..i + buf.len());
             i += buf.len();
         } else {
-            i += 1;
+            // 跳过一个完整的字符，避免落在多字节字符的中间
+            i += temp.chars().next().map_or(1, char::len_utf8);
         }
     }
-    pinyins
+    ranges
 }
 
 pub fn pinyin_split_by_trie_tokenizer(value: impl AsRef<str>) -> String {
@@ -111,11 +168,51 @@ pub fn pinyin_split_by_trie_tokenizer(value: impl AsRef<str>) -> String {
 ///
 /// 如 jinan => ["ji nan", "jin an"]；zhang => ["zhang"]；zhangssan => []
 ///
+/// [`DEFAULT_SEPARATORS`] 中的字符是强制的切分位置，如 jin'an => ["jin an"]
+///
 /// 算法来自 https://github.com/xmflswood/pinyin-match/blob/master/src/core.js#L57
 pub fn pinyin_split(value: impl AsRef<str>) -> Vec<String> {
-    let pinyin = value.as_ref();
+    pinyin_split_with_separators(value, DEFAULT_SEPARATORS)
+}
+
+/// 和 [`pinyin_split`] 一样，但是使用 separators 作为强制的切分位置
+///
+/// 每一段分别切分，再把每一段的切分方式组合起来，有一段无法切分时返回空
+pub fn pinyin_split_with_separators(value: impl AsRef<str>, separators: &[char]) -> Vec<String> {
+    let mut all_pinyin_slice = Vec::<String>::new();
+    for part in value
+        .as_ref()
+        .split(separators)
+        .filter(|part| !part.is_empty())
+    {
+        let part_slice = split_part(part);
+        if part_slice.is_empty() {
+            return Vec::new();
+        }
+        all_pinyin_slice = if all_pinyin_slice.is_empty() {
+            part_slice
+        } else {
+            all_pinyin_slice
+                .iter()
+                .flat_map(|prefix| {
+                    part_slice
+                        .iter()
+                        .map(move |slice| format!("{prefix} {slice}"))
+                })
+                .collect()
+        };
+    }
+    all_pinyin_slice
+}
+
+/// 切分一段不包含分隔符的拼音
+fn split_part(pinyin: &str) -> Vec<String> {
     // 去除声调
     let pinyin = to_plain(pinyin);
+    // 拼音都是 ASCII 字符，避免在多字节字符的中间切片
+    if !pinyin.is_ascii() {
+        return Vec::new();
+    }
     // 用来保存所有符合要求的切分之后的拼音
     let mut all_pinyin_slice = Vec::<String>::new();
     // 记录从某个位置开始之后的子串存在有效的拼音分割方案
@@ -146,6 +243,15 @@ fn to_plain(pinyin: &str) -> String {
         .collect::<String>()
 }
 
+/// 把拼音拆成声母和剩余的部分，零声母的拼音返回空的声母
+fn split_initial(pinyin: &str) -> (&str, &str) {
+    INITIALS
+        .iter()
+        .find(|initial| pinyin.len() > initial.len() && pinyin.starts_with(*initial))
+        .map(|initial| pinyin.split_at(initial.len()))
+        .unwrap_or(("", pinyin))
+}
+
 fn get_all_pinyin_slice(
     start: usize,
     pinyin: &str,
@@ -198,7 +304,23 @@ mod tests {
         assert_eq!(split, ["ji nan", "jin an"]);
         let pinyin = "zhangssan";
         let split = super::pinyin_split(pinyin);
-        assert!(split.is_empty())
+        assert!(split.is_empty());
+        // 分隔符是强制的切分位置
+        let split = super::pinyin_split("ji'nan");
+        assert_eq!(split, ["ji nan"]);
+        let split = super::pinyin_split("jin’an");
+        assert_eq!(split, ["jin an"]);
+        let split = super::pinyin_split("xian-jinan");
+        assert_eq!(
+            split,
+            ["xi an ji nan", "xi an jin an", "xian ji nan", "xian jin an"]
+        );
+        let split = super::pinyin_split("zhan'gssan");
+        assert!(split.is_empty());
+        let split = super::pinyin_split_with_separators("ji nan", &[' ']);
+        assert_eq!(split, ["ji nan"]);
+        let split = super::pinyin_split("你好");
+        assert!(split.is_empty());
     }
 
     #[test]
@@ -248,5 +370,28 @@ mod tests {
         let value = "12233dddggsddfgdfgfdsddd";
         let pinyins = super::pinyin_tokenize(value);
         assert!(pinyins.is_empty());
+        // 分隔符两边的字母不会组成同一个拼音
+        let value = "xi'an";
+        let pinyins = super::pinyin_tokenize(value);
+        assert_eq!(pinyins, ["xi", "an"]);
+        let value = "xian";
+        let pinyins = super::pinyin_tokenize(value);
+        assert_eq!(pinyins, ["xian"]);
+        let value = "fang'an";
+        let pinyins = super::pinyin_tokenize(value);
+        assert_eq!(pinyins, ["fang", "an"]);
+        let value = "gu-ang";
+        let pinyins = super::pinyin_tokenize(value);
+        assert_eq!(pinyins, ["gu", "ang"]);
+        let value = "pi’ao";
+        let pinyins = super::pinyin_tokenize(value);
+        assert_eq!(pinyins, ["pi", "ao"]);
+        let value = "xi/an";
+        let pinyins = super::pinyin_tokenize_with_separators(value, &['/']);
+        assert_eq!(pinyins, ["xi", "an"]);
+        // 不是分隔符的非 ASCII 字符会被跳过
+        let value = "ni你hao";
+        let pinyins = super::pinyin_tokenize(value);
+        assert_eq!(pinyins, ["ni", "hao"]);
     }
 }
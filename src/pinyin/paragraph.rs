@@ -0,0 +1,251 @@
+use crate::pinyin::{AsciiPolicy, DEFAULT_SEPARATORS, Token, pinyin_tokenize_mixed};
+use std::ops::Range;
+
+/// 段落中的一段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunk<'a> {
+    /// 用空白字符分隔的拼音，如 "wo men jin tian"，每个词都可以完整地切分成拼音
+    Pinyin(&'a str),
+    /// 标点符号，转换时使用 [`FullWidthPunct`] 转换成全角的中文标点
+    Punct(char),
+    /// 原样保留的文字，如数字、英文单词和汉字
+    Text(&'a str),
+}
+
+/// 切分段落时的一个片段
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Pinyin(Range<usize>),
+    Punct(char),
+    Space(Range<usize>),
+    Text(Range<usize>),
+}
+
+/// 把一段包含标点、数字和其它文字的拼音切分成 [`Chunk`]
+///
+/// 由 ASCII 字母组成的词（可以包含 [`DEFAULT_SEPARATORS`]）可以完整切分成拼音时才是拼音，否则原样保留，
+/// 不区分大小写。拼音之间、标点前后的空白字符会被去掉，其它空白字符原样保留。
+/// 数字之间的 `.`、`,` 和 `:` 不是标点，如 3.14
+///
+/// ```
+/// use pinyinchch::pinyin::{Chunk, split_paragraph};
+///
+/// assert_eq!(
+///     split_paragraph("wo men jin tian qu bei jing, 3 dian"),
+///     [
+///         Chunk::Pinyin("wo men jin tian qu bei jing"),
+///         Chunk::Punct(','),
+///         Chunk::Text("3 "),
+///         Chunk::Pinyin("dian"),
+///     ]
+/// );
+/// ```
+pub fn split_paragraph(text: &str) -> Vec<Chunk<'_>> {
+    split_paragraph_with_policy(text, AsciiPolicy::default())
+}
+
+/// 和 [`split_paragraph`] 相同，但是使用 policy 判断一个词是拼音还是英文，见 [`AsciiPolicy`]
+pub fn split_paragraph_with_policy(text: &str, policy: AsciiPolicy) -> Vec<Chunk<'_>> {
+    let pieces = scan(text, policy);
+
+    let mut merged: Vec<Piece> = Vec::with_capacity(pieces.len());
+    for (idx, piece) in pieces.iter().enumerate() {
+        let piece = match piece {
+            Piece::Space(range) => {
+                let prev = idx.checked_sub(1).map(|idx| &pieces[idx]);
+                let next = pieces.get(idx + 1);
+                let near_punct =
+                    matches!(prev, Some(Piece::Punct(_))) || matches!(next, Some(Piece::Punct(_)));
+                let between_pinyin = matches!(prev, Some(Piece::Pinyin(_)))
+                    && matches!(next, Some(Piece::Pinyin(_)));
+                if near_punct || between_pinyin {
+                    continue;
+                }
+                Piece::Text(range.clone())
+            }
+            piece => piece.clone(),
+        };
+        // 相邻的拼音合并成一段，相邻的文字合并成一段
+        match (merged.last_mut(), piece) {
+            (Some(Piece::Pinyin(last)), Piece::Pinyin(range))
+            | (Some(Piece::Text(last)), Piece::Text(range))
+                if last.end <= range.start && text[last.end..range.start].trim().is_empty() =>
+            {
+                last.end = range.end;
+            }
+            (_, piece) => merged.push(piece),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Pinyin(range) => Chunk::Pinyin(&text[range]),
+            Piece::Punct(ch) => Chunk::Punct(ch),
+            Piece::Space(range) | Piece::Text(range) => Chunk::Text(&text[range]),
+        })
+        .collect()
+}
+
+/// 把 [`pinyin_tokenize_mixed`] 的结果转换成拼音、空白、标点和其它文字
+fn scan(text: &str, policy: AsciiPolicy) -> Vec<Piece> {
+    let tokens = pinyin_tokenize_mixed(text, policy);
+    tokens
+        .iter()
+        .enumerate()
+        .map(|(idx, token)| match token {
+            Token::Pinyin(range, _) => Piece::Pinyin(range.clone()),
+            Token::Whitespace(range, _) => Piece::Space(range.clone()),
+            Token::Punct(range, punct) => {
+                let prev = idx.checked_sub(1).map(|idx| &tokens[idx]);
+                let next = tokens.get(idx + 1);
+                let is_letters = |token: Option<&Token>| {
+                    matches!(token, Some(Token::Pinyin(..) | Token::Latin(..)))
+                };
+                let ch = punct.chars().next().unwrap_or_default();
+                // 词中间的分隔符属于这个词
+                if DEFAULT_SEPARATORS.contains(&ch) && is_letters(prev) && is_letters(next) {
+                    if prev.is_some_and(Token::is_pinyin) && next.is_some_and(Token::is_pinyin) {
+                        Piece::Pinyin(range.clone())
+                    } else {
+                        Piece::Text(range.clone())
+                    }
+                } else if is_punct(ch) {
+                    Piece::Punct(ch)
+                } else {
+                    Piece::Text(range.clone())
+                }
+            }
+            token => Piece::Text(token.range()),
+        })
+        .collect()
+}
+
+/// 可以转换成全角的 ASCII 标点和中文标点
+fn is_punct(ch: char) -> bool {
+    FullWidthPunct::ascii_to_full_width(ch).is_some()
+        || matches!(
+            ch,
+            '"' | '\''
+                | '，'
+                | '。'
+                | '？'
+                | '！'
+                | '：'
+                | '；'
+                | '、'
+                | '（'
+                | '）'
+                | '【'
+                | '】'
+                | '《'
+                | '》'
+                | '“'
+                | '”'
+                | '‘'
+                | '’'
+                | '…'
+                | '～'
+        )
+}
+
+/// 把 ASCII 标点转换成全角的中文标点
+///
+/// 双引号和单引号交替转换成左引号和右引号，所以同一段文字需要使用同一个转换器
+#[derive(Debug, Clone, Default)]
+pub struct FullWidthPunct {
+    double_quote_open: bool,
+    single_quote_open: bool,
+}
+
+impl FullWidthPunct {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 转换一个标点，不是 ASCII 标点时原样返回
+    pub fn convert(&mut self, ch: char) -> char {
+        match ch {
+            '"' => {
+                self.double_quote_open = !self.double_quote_open;
+                if self.double_quote_open { '“' } else { '”' }
+            }
+            '\'' => {
+                self.single_quote_open = !self.single_quote_open;
+                if self.single_quote_open { '‘' } else { '’' }
+            }
+            ch => Self::ascii_to_full_width(ch).unwrap_or(ch),
+        }
+    }
+
+    fn ascii_to_full_width(ch: char) -> Option<char> {
+        let full_width = match ch {
+            ',' => '，',
+            '.' => '。',
+            '?' => '？',
+            '!' => '！',
+            ':' => '：',
+            ';' => '；',
+            '(' => '（',
+            ')' => '）',
+            '[' => '【',
+            ']' => '】',
+            '<' => '《',
+            '>' => '》',
+            '~' => '～',
+            _ => return None,
+        };
+        Some(full_width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Chunk::{Pinyin, Punct, Text};
+    use super::{FullWidthPunct, split_paragraph};
+
+    #[test]
+    fn test_split_paragraph() {
+        assert_eq!(
+            split_paragraph("wo men jin tian qu Bei Jing, ran hou..."),
+            [
+                Pinyin("wo men jin tian qu Bei Jing"),
+                Punct(','),
+                Pinyin("ran hou"),
+                Punct('.'),
+                Punct('.'),
+                Punct('.'),
+            ]
+        );
+        // 数字和不是拼音的单词原样保留，数字中的小数点不是标点
+        assert_eq!(
+            split_paragraph("xi'an 3.14 hello world ni hao"),
+            [
+                Pinyin("xi'an"),
+                Text(" 3.14 hello world "),
+                Pinyin("ni hao"),
+            ]
+        );
+        assert_eq!(
+            split_paragraph("“ni hao” 你好!"),
+            [
+                Punct('“'),
+                Pinyin("ni hao"),
+                Punct('”'),
+                Text("你好"),
+                Punct('!'),
+            ]
+        );
+        assert!(split_paragraph("").is_empty());
+    }
+
+    #[test]
+    fn test_full_width_punct() {
+        let mut punct = FullWidthPunct::new();
+        let text = "\"a\", 'b'?!"
+            .chars()
+            .map(|ch| punct.convert(ch))
+            .collect::<String>();
+        assert_eq!(text, "“a”， ‘b’？！");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::pinyin::tone::plain_char;
+use crate::pinyin::trie_tokenizer::PINYIN_TRIE;
+use crate::pinyin::{DEFAULT_SEPARATORS, Tone, pinyin_split};
+use std::ops::Range;
+
+/// 一个拼音在原始输入中的字节范围、原始文字和声调
+pub type PinyinSpan<'a> = (Range<usize>, &'a str, Option<Tone>);
+
+/// 转换成小写并去除声调之后的字符串，以及每个字节在原始字符串中的位置和声调
+struct Plain {
+    text: String,
+    /// 比 text 多一项，最后一项是原始字符串的长度
+    offsets: Vec<usize>,
+    tones: Vec<Option<Tone>>,
+}
+
+impl Plain {
+    fn new(value: &str) -> Self {
+        let mut text = String::with_capacity(value.len());
+        let mut offsets = Vec::with_capacity(value.len() + 1);
+        let mut tones = Vec::with_capacity(value.len());
+        for (offset, ch) in value.char_indices() {
+            let start = text.len();
+            // 大写的带声调的韵母先转换成小写，再去除声调
+            let mut tone = None;
+            for lower in ch.to_lowercase() {
+                text.push(plain_char(lower));
+                tone = tone.or(Tone::from_diacritic(lower));
+            }
+            offsets.resize(text.len(), offset);
+            tones.resize(text.len(), None);
+            tones[start] = tone;
+        }
+        offsets.push(value.len());
+        Plain {
+            text,
+            offsets,
+            tones,
+        }
+    }
+
+    /// 去除声调之后的 range 对应的原始范围，以及其中带声调的韵母的声调
+    fn span(&self, range: Range<usize>) -> (Range<usize>, Option<Tone>) {
+        let tone = self.tones[range.clone()].iter().find_map(|tone| *tone);
+        (self.offsets[range.start]..self.offsets[range.end], tone)
+    }
+}
+
+/// 和 [`pinyin_tokenize`](crate::pinyin::pinyin_tokenize) 一样借助前缀树切分拼音，
+/// 返回每个拼音在原始输入中的字节范围、原始文字和声调，不区分大小写
+///
+/// 输入可以包含带声调的韵母和拼音后面的数字声调，数字声调包含在拼音的范围中，如
+/// `"nǐ hao3"` => `[(0..3, "nǐ", Some(Third)), (4..8, "hao3", Some(Third))]`
+pub fn pinyin_tokenize_spans(value: &str) -> Vec<PinyinSpan<'_>> {
+    let plain = Plain::new(value);
+    let mut spans = Vec::new();
+    let len = plain.text.len();
+    let mut i = 0;
+    while i < len {
+        let temp = &plain.text[i..];
+        let (buf, is_success) = PINYIN_TRIE.find(temp, DEFAULT_SEPARATORS);
+        if is_success && !buf.is_empty() {
+            let mut end = i + buf.len();
+            let (_, mut tone) = plain.span(i..end);
+            if tone.is_none()
+                && let Some(digit) = plain.text[end..].chars().next().and_then(Tone::from_digit)
+            {
+                tone = Some(digit);
+                end += 1;
+            }
+            let range = plain.offsets[i]..plain.offsets[end];
+            spans.push((range.clone(), &value[range], tone));
+            i = end;
+        } else {
+            // 跳过一个完整的字符，避免落在多字节字符的中间
+            i += temp.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    spans
+}
+
+/// 和 [`pinyin_split`] 一样列出所有的切分方式，每种切分方式中的每个拼音带有在原始输入中的字节范围、原始文字和声调，不区分大小写
+///
+/// 输入可以包含带声调的韵母，如 `"xīan"` 有 `[(0..3, "xī", Some(First)), (3..5, "an", None)]` 和 `[(0..5, "xīan", Some(First))]` 两种切分方式
+pub fn pinyin_split_spans(value: &str) -> Vec<Vec<PinyinSpan<'_>>> {
+    let plain = Plain::new(value);
+    pinyin_split(&plain.text)
+        .into_iter()
+        .map(|split| {
+            let mut spans = Vec::new();
+            let mut cursor = 0;
+            for syllable in split.split(' ') {
+                // 跳过拼音之前的分隔符
+                while let Some(ch) = plain.text[cursor..].chars().next()
+                    && DEFAULT_SEPARATORS.contains(&ch)
+                {
+                    cursor += ch.len_utf8();
+                }
+                let (range, tone) = plain.span(cursor..cursor + syllable.len());
+                spans.push((range.clone(), &value[range], tone));
+                cursor += syllable.len();
+            }
+            spans
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pinyin_split_spans, pinyin_tokenize_spans};
+    use crate::pinyin::Tone;
+
+    #[test]
+    fn test_pinyin_tokenize_spans() {
+        assert_eq!(
+            pinyin_tokenize_spans("nǐ hao3"),
+            [
+                (0..3, "nǐ", Some(Tone::Third)),
+                (4..8, "hao3", Some(Tone::Third)),
+            ]
+        );
+        // ü 在原始输入中占两个字节
+        assert_eq!(
+            pinyin_tokenize_spans("lǚyóu，xi'an"),
+            [
+                (0..3, "lǚ", Some(Tone::Third)),
+                (3..7, "yóu", Some(Tone::Second)),
+                (10..12, "xi", None),
+                (13..15, "an", None),
+            ]
+        );
+        assert!(pinyin_tokenize_spans("").is_empty());
+
+        // 不区分大小写，原始文字保留大小写
+        assert_eq!(
+            pinyin_tokenize_spans("Nǐ HǍO"),
+            [
+                (0..3, "Nǐ", Some(Tone::Third)),
+                (4..8, "HǍO", Some(Tone::Third)),
+            ]
+        );
+        assert_eq!(
+            pinyin_tokenize_spans("NiHao"),
+            [(0..2, "Ni", None), (2..5, "Hao", None)]
+        );
+    }
+
+    #[test]
+    fn test_pinyin_split_spans() {
+        let splits = pinyin_split_spans("jīnán");
+        assert_eq!(splits.len(), 2);
+        assert!(splits.contains(&vec![
+            (0..3, "jī", Some(Tone::First)),
+            (3..7, "nán", Some(Tone::Second)),
+        ]));
+        assert!(splits.contains(&vec![
+            (0..4, "jīn", Some(Tone::First)),
+            (4..7, "án", Some(Tone::Second)),
+        ]));
+        assert_eq!(
+            pinyin_split_spans("xi’an"),
+            [vec![(0..2, "xi", None), (5..7, "an", None)]]
+        );
+        assert!(pinyin_split_spans("zhangssan").is_empty());
+
+        let splits = pinyin_split_spans("JĪNán");
+        assert_eq!(splits.len(), 2);
+        assert!(splits.contains(&vec![
+            (0..4, "JĪN", Some(Tone::First)),
+            (4..7, "án", Some(Tone::Second)),
+        ]));
+        let splits = pinyin_split_spans("XiAn");
+        assert_eq!(splits.len(), 2);
+        assert!(splits.contains(&vec![(0..2, "Xi", None), (2..4, "An", None)]));
+        assert!(splits.contains(&vec![(0..4, "XiAn", None)]));
+    }
+}
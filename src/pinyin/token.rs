@@ -0,0 +1,353 @@
+use crate::pinyin::tone::plain_char;
+use crate::pinyin::{DEFAULT_SEPARATORS, pinyin_split_spans, pinyin_tokenize_spans};
+use std::ops::Range;
+
+/// 混合文本中的一个词元，记录在原始文本中的字节范围和对应的文字
+///
+/// 所有词元按顺序拼接起来就是原始文本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// 一个拼音，文字保留原来的大小写和带声调的韵母
+    Pinyin(Range<usize>, &'a str),
+    /// 不是拼音的字母，如英文单词
+    Latin(Range<usize>, &'a str),
+    /// 连续的 ASCII 数字，可以包含数字之间的 `.`、`,` 和 `:`，如 3.14
+    Number(Range<usize>, &'a str),
+    /// 一个标点符号，包括拼音之间的分隔符
+    Punct(Range<usize>, &'a str),
+    /// 连续的汉字
+    Hanzi(Range<usize>, &'a str),
+    /// 连续的空白字符
+    Whitespace(Range<usize>, &'a str),
+    /// 其它字符，如表情符号
+    Other(Range<usize>, &'a str),
+}
+
+impl<'a> Token<'a> {
+    /// 在原始文本中的字节范围
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            Token::Pinyin(range, _)
+            | Token::Latin(range, _)
+            | Token::Number(range, _)
+            | Token::Punct(range, _)
+            | Token::Hanzi(range, _)
+            | Token::Whitespace(range, _)
+            | Token::Other(range, _) => range.clone(),
+        }
+    }
+
+    pub fn text(&self) -> &'a str {
+        match self {
+            Token::Pinyin(_, text)
+            | Token::Latin(_, text)
+            | Token::Number(_, text)
+            | Token::Punct(_, text)
+            | Token::Hanzi(_, text)
+            | Token::Whitespace(_, text)
+            | Token::Other(_, text) => text,
+        }
+    }
+
+    pub fn is_pinyin(&self) -> bool {
+        matches!(self, Token::Pinyin(..))
+    }
+}
+
+/// 判断一串 ASCII 字母是拼音还是英文的策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AsciiPolicy {
+    /// 整串字母都可以切分成有效的拼音时才是拼音，否则是英文，不区分大小写，如 xian 是拼音，good 是英文
+    #[default]
+    FullCoverage,
+    /// 尽可能切分出拼音，剩下的字母是英文，和 [`pinyin_tokenize`](crate::pinyin::pinyin_tokenize) 一致，
+    /// 如 good => o、o 是拼音，g、d 是英文
+    Greedy,
+    /// 所有的字母都是英文
+    Latin,
+}
+
+/// 把混合了拼音、英文、数字、标点和汉字的文本切分成 [`Token`]，如
+/// `"wo xiang mai iPhone 15"` => `[Pinyin("wo"), Whitespace, Pinyin("xiang"), Whitespace, Pinyin("mai"), Whitespace, Latin("iPhone"), Whitespace, Number("15")]`
+///
+/// 由 ASCII 字母和带声调的韵母组成的一串字母（可以包含 [`DEFAULT_SEPARATORS`]）按照 policy 判断是拼音还是英文，
+/// 判断时不区分大小写并去除声调，如 `"Nǐ hǎo"` 中的 `Nǐ` 和 `hǎo` 都是拼音。拼音中的分隔符是单独的标点
+pub fn pinyin_tokenize_mixed(value: &str, policy: AsciiPolicy) -> Vec<Token<'_>> {
+    let chars = value.char_indices().collect::<Vec<_>>();
+    let offset = |idx: usize| chars.get(idx).map_or(value.len(), |(offset, _)| *offset);
+    let is_ascii_digit = |idx: usize| chars.get(idx).is_some_and(|(_, ch)| ch.is_ascii_digit());
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (start, ch) = chars[i];
+        let mut j = i + 1;
+        if is_letter(ch) {
+            while j < chars.len() {
+                let next = chars[j].1;
+                if is_letter(next) {
+                    j += 1;
+                } else if DEFAULT_SEPARATORS.contains(&next)
+                    && chars.get(j + 1).is_some_and(|(_, ch)| is_letter(*ch))
+                {
+                    j += 2;
+                } else {
+                    break;
+                }
+            }
+            tokenize_letters(value, start..offset(j), policy, &mut tokens);
+            i = j;
+            continue;
+        }
+
+        let range = if ch.is_ascii_digit() {
+            while j < chars.len()
+                && (chars[j].1.is_ascii_digit()
+                    || (matches!(chars[j].1, '.' | ',' | ':') && is_ascii_digit(j + 1)))
+            {
+                j += 1;
+            }
+            start..offset(j)
+        } else if ch.is_whitespace() {
+            while j < chars.len() && chars[j].1.is_whitespace() {
+                j += 1;
+            }
+            start..offset(j)
+        } else if is_hanzi(ch) {
+            while j < chars.len() && is_hanzi(chars[j].1) {
+                j += 1;
+            }
+            start..offset(j)
+        } else if is_punct(ch) {
+            start..offset(j)
+        } else {
+            while j < chars.len() && is_other(chars[j].1) {
+                j += 1;
+            }
+            start..offset(j)
+        };
+        let text = &value[range.clone()];
+        tokens.push(if ch.is_ascii_digit() {
+            Token::Number(range, text)
+        } else if ch.is_whitespace() {
+            Token::Whitespace(range, text)
+        } else if is_hanzi(ch) {
+            Token::Hanzi(range, text)
+        } else if is_punct(ch) {
+            Token::Punct(range, text)
+        } else {
+            Token::Other(range, text)
+        });
+        i = j;
+    }
+    tokens
+}
+
+/// 按照 policy 切分一串字母，range 是字母串在 value 中的范围
+fn tokenize_letters<'a>(
+    value: &'a str,
+    range: Range<usize>,
+    policy: AsciiPolicy,
+    tokens: &mut Vec<Token<'a>>,
+) {
+    let word = &value[range.clone()];
+    let pinyin_ranges = match policy {
+        AsciiPolicy::FullCoverage => full_coverage_ranges(word),
+        AsciiPolicy::Greedy => pinyin_tokenize_spans(word)
+            .into_iter()
+            .map(|(range, _, _)| range)
+            .collect(),
+        AsciiPolicy::Latin => Vec::new(),
+    };
+
+    let mut cursor = range.start;
+    for pinyin in pinyin_ranges {
+        let pinyin = range.start + pinyin.start..range.start + pinyin.end;
+        push_gap(value, cursor..pinyin.start, tokens);
+        tokens.push(Token::Pinyin(pinyin.clone(), &value[pinyin.clone()]));
+        cursor = pinyin.end;
+    }
+    push_gap(value, cursor..range.end, tokens);
+}
+
+/// 拼音之间剩下的字母是英文，分隔符是标点
+fn push_gap<'a>(value: &'a str, gap: Range<usize>, tokens: &mut Vec<Token<'a>>) {
+    let mut letters_start = gap.start;
+    for (offset, ch) in value[gap.clone()].char_indices() {
+        if !DEFAULT_SEPARATORS.contains(&ch) {
+            continue;
+        }
+        let offset = gap.start + offset;
+        if letters_start < offset {
+            tokens.push(Token::Latin(
+                letters_start..offset,
+                &value[letters_start..offset],
+            ));
+        }
+        let end = offset + ch.len_utf8();
+        tokens.push(Token::Punct(offset..end, &value[offset..end]));
+        letters_start = end;
+    }
+    if letters_start < gap.end {
+        tokens.push(Token::Latin(
+            letters_start..gap.end,
+            &value[letters_start..gap.end],
+        ));
+    }
+}
+
+/// 整串字母都可以切分成拼音时，返回每个拼音在 word 中的范围，否则为空
+///
+/// 有多种切分方式时使用拼音数量最少的一种，如 xiang => xiang 而不是 xi ang
+fn full_coverage_ranges(word: &str) -> Vec<Range<usize>> {
+    pinyin_split_spans(word)
+        .into_iter()
+        .min_by_key(Vec::len)
+        .map(|spans| spans.into_iter().map(|(range, _, _)| range).collect())
+        .unwrap_or_default()
+}
+
+/// 拼音中的字母，包括 ASCII 字母和带声调的韵母，如 ǎ、Ǘ、ü
+fn is_letter(ch: char) -> bool {
+    ch.is_ascii_alphabetic()
+        || ch
+            .to_lowercase()
+            .all(|lower| plain_char(lower).is_ascii_alphabetic())
+}
+
+/// 常用的汉字，包括 CJK 统一表意文字及其扩展 A、兼容表意文字和扩展 B 到扩展 F
+fn is_hanzi(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{4E00}'..='\u{9FFF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2EBEF}'
+    )
+}
+
+/// ASCII 标点和常用的中文标点
+fn is_punct(ch: char) -> bool {
+    ch.is_ascii_punctuation()
+        || matches!(
+            ch,
+            '，' | '。'
+                | '？'
+                | '！'
+                | '：'
+                | '；'
+                | '、'
+                | '（'
+                | '）'
+                | '【'
+                | '】'
+                | '《'
+                | '》'
+                | '“'
+                | '”'
+                | '‘'
+                | '’'
+                | '…'
+                | '～'
+                | '—'
+                | '·'
+        )
+}
+
+/// 不属于其它类型的字符
+fn is_other(ch: char) -> bool {
+    !(is_letter(ch) || ch.is_ascii_digit() || ch.is_whitespace() || is_hanzi(ch) || is_punct(ch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Token::{Hanzi, Latin, Number, Other, Pinyin, Punct, Whitespace};
+    use super::{AsciiPolicy, pinyin_tokenize_mixed};
+
+    #[test]
+    fn test_pinyin_tokenize_mixed() {
+        let text = "wo xiang mai iPhone 15, 3.5元 Xi'an 😀";
+        let tokens = pinyin_tokenize_mixed(text, AsciiPolicy::FullCoverage);
+        assert_eq!(
+            tokens,
+            [
+                Pinyin(0..2, "wo"),
+                Whitespace(2..3, " "),
+                Pinyin(3..8, "xiang"),
+                Whitespace(8..9, " "),
+                Pinyin(9..12, "mai"),
+                Whitespace(12..13, " "),
+                Latin(13..19, "iPhone"),
+                Whitespace(19..20, " "),
+                Number(20..22, "15"),
+                Punct(22..23, ","),
+                Whitespace(23..24, " "),
+                Number(24..27, "3.5"),
+                Hanzi(27..30, "元"),
+                Whitespace(30..31, " "),
+                Pinyin(31..33, "Xi"),
+                Punct(33..34, "'"),
+                Pinyin(34..36, "an"),
+                Whitespace(36..37, " "),
+                Other(37..41, "😀"),
+            ]
+        );
+        // 所有词元拼接起来就是原始文本
+        let joined = tokens.iter().map(|token| token.text()).collect::<String>();
+        assert_eq!(joined, text);
+        assert!(pinyin_tokenize_mixed("", AsciiPolicy::FullCoverage).is_empty());
+    }
+
+    #[test]
+    fn test_ascii_policy() {
+        assert_eq!(
+            pinyin_tokenize_mixed("good xian", AsciiPolicy::FullCoverage),
+            [
+                Latin(0..4, "good"),
+                Whitespace(4..5, " "),
+                Pinyin(5..9, "xian"),
+            ]
+        );
+        assert_eq!(
+            pinyin_tokenize_mixed("good", AsciiPolicy::Greedy),
+            [
+                Latin(0..1, "g"),
+                Pinyin(1..2, "o"),
+                Pinyin(2..3, "o"),
+                Latin(3..4, "d"),
+            ]
+        );
+        assert_eq!(
+            pinyin_tokenize_mixed("ni'hao", AsciiPolicy::Latin),
+            [Latin(0..2, "ni"), Punct(2..3, "'"), Latin(3..6, "hao")]
+        );
+    }
+
+    #[test]
+    fn test_pinyin_tokenize_mixed_tone() {
+        // 带声调的韵母是字母，不区分大小写
+        assert_eq!(
+            pinyin_tokenize_mixed("nǐ HǍO, lǚyóu café", AsciiPolicy::FullCoverage),
+            [
+                Pinyin(0..3, "nǐ"),
+                Whitespace(3..4, " "),
+                Pinyin(4..8, "HǍO"),
+                Punct(8..9, ","),
+                Whitespace(9..10, " "),
+                Pinyin(10..13, "lǚ"),
+                Pinyin(13..17, "yóu"),
+                Whitespace(17..18, " "),
+                Latin(18..23, "café"),
+            ]
+        );
+        assert_eq!(
+            pinyin_tokenize_mixed("gǒod", AsciiPolicy::Greedy),
+            [
+                Latin(0..1, "g"),
+                Pinyin(1..3, "ǒ"),
+                Pinyin(3..4, "o"),
+                Latin(4..5, "d"),
+            ]
+        );
+    }
+}
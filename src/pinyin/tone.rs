@@ -0,0 +1,421 @@
+use crate::error::{DataError, InvalidLineSnafu, ReadFileSnafu, ReadLineSnafu};
+use crate::pinyin::trie_tokenizer::PINYIN_TRIE;
+use crate::pinyin::{DEFAULT_SEPARATORS, TONE_TO_PLAIN, split_initial};
+use snafu::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// 声调和输入不一致时，[`TonedHmm`](crate::hmm::TonedHmm) 和 [`TonedDag`](crate::dag::TonedDag) 默认乘以的惩罚系数
+pub const DEFAULT_TONE_PENALTY: f64 = 0.1;
+
+/// 声调，轻声记作 5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Tone {
+    First = 1,
+    Second = 2,
+    Third = 3,
+    Fourth = 4,
+    Neutral = 5,
+}
+
+impl Tone {
+    /// 从数字 1 到 5 得到声调
+    pub fn from_number(number: u8) -> Option<Self> {
+        match number {
+            1 => Some(Tone::First),
+            2 => Some(Tone::Second),
+            3 => Some(Tone::Third),
+            4 => Some(Tone::Fourth),
+            5 => Some(Tone::Neutral),
+            _ => None,
+        }
+    }
+
+    /// 从字符 '1' 到 '5' 得到声调
+    pub fn from_digit(ch: char) -> Option<Self> {
+        Self::from_number(ch.to_digit(10)? as u8)
+    }
+
+    /// 从带声调的韵母得到声调，如 ǎ => 3
+    pub fn from_diacritic(ch: char) -> Option<Self> {
+        match ch {
+            'ā' | 'ē' | 'ī' | 'ō' | 'ū' | 'ǖ' => Some(Tone::First),
+            'á' | 'é' | 'í' | 'ó' | 'ú' | 'ǘ' | 'ế' | 'ń' | 'ḿ' => Some(Tone::Second),
+            'ǎ' | 'ě' | 'ǐ' | 'ǒ' | 'ǔ' | 'ǚ' | 'ň' => Some(Tone::Third),
+            'à' | 'è' | 'ì' | 'ò' | 'ù' | 'ǜ' | 'ề' | 'ǹ' => Some(Tone::Fourth),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self) -> u8 {
+        *self as u8
+    }
+}
+
+/// 带声调的拼音，拼音部分不带声调，没有输入声调时 tone 为 None
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TonedPinyin {
+    pub pinyin: String,
+    pub tone: Option<Tone>,
+}
+
+impl TonedPinyin {
+    pub fn new(pinyin: impl Into<String>, tone: Option<Tone>) -> Self {
+        Self {
+            pinyin: pinyin.into(),
+            tone,
+        }
+    }
+
+    /// 解析一个拼音，声调可以是结尾的数字，也可以是带声调的韵母，如 lv3、lǚ、xíng
+    ///
+    /// 拼音会被转换成小写，ü 转换成 v
+    pub fn parse(value: impl AsRef<str>) -> Self {
+        let value = value.as_ref().trim().to_lowercase().replace("u:", "v");
+        let (value, mut tone) = match value.chars().last().and_then(Tone::from_digit) {
+            Some(tone) => (&value[..value.len() - 1], Some(tone)),
+            None => (value.as_str(), None),
+        };
+        let mut pinyin = String::with_capacity(value.len());
+        for ch in value.chars() {
+            tone = tone.or(Tone::from_diacritic(ch));
+            pinyin.push(plain_char(ch));
+        }
+        Self { pinyin, tone }
+    }
+}
+
+/// 输出拼音的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PinyinStyle {
+    /// 不带声调，如 zhong
+    #[default]
+    Plain,
+    /// 带声调符号，如 zhōng、lǚ
+    ToneMarks,
+    /// 数字声调，如 zhong1
+    ToneNumbers,
+    /// 只有声母，如 zh，零声母的拼音使用第一个字母，如 an => a
+    Initials,
+}
+
+impl TonedPinyin {
+    /// 按照 style 输出拼音，没有声调时 ToneMarks 和 ToneNumbers 输出不带声调的拼音
+    pub fn format(&self, style: PinyinStyle) -> String {
+        match style {
+            PinyinStyle::Plain => self.pinyin.clone(),
+            PinyinStyle::ToneMarks => self.with_tone_mark(),
+            PinyinStyle::ToneNumbers => self.to_string(),
+            PinyinStyle::Initials => match split_initial(&self.pinyin) {
+                ("", rest) => rest.chars().take(1).collect(),
+                (initial, _) => initial.to_owned(),
+            },
+        }
+    }
+
+    /// 在韵母上标注声调，a 和 e 优先，ou 标在 o 上，其它情况标在最后一个韵母上，v 输出为 ü
+    pub fn with_tone_mark(&self) -> String {
+        let pinyin = self.pinyin.replace('v', "ü");
+        let Some(idx) = self
+            .tone
+            .filter(|tone| *tone != Tone::Neutral)
+            .and_then(|_| {
+                pinyin
+                    .find('a')
+                    .or_else(|| pinyin.find('e'))
+                    .or_else(|| pinyin.find("ou"))
+                    .or_else(|| pinyin.rfind(['i', 'o', 'u', 'ü']))
+            })
+        else {
+            return pinyin;
+        };
+        let tone = self.tone.map_or(0, |tone| tone.number() as usize - 1);
+        let vowel = pinyin[idx..].chars().next().unwrap_or_default();
+        let marked = match vowel {
+            'a' => ['ā', 'á', 'ǎ', 'à'][tone],
+            'e' => ['ē', 'é', 'ě', 'è'][tone],
+            'i' => ['ī', 'í', 'ǐ', 'ì'][tone],
+            'o' => ['ō', 'ó', 'ǒ', 'ò'][tone],
+            'u' => ['ū', 'ú', 'ǔ', 'ù'][tone],
+            'ü' => ['ǖ', 'ǘ', 'ǚ', 'ǜ'][tone],
+            _ => vowel,
+        };
+        format!(
+            "{}{marked}{}",
+            &pinyin[..idx],
+            &pinyin[idx + vowel.len_utf8()..]
+        )
+    }
+}
+
+impl fmt::Display for TonedPinyin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tone {
+            Some(tone) => write!(f, "{}{}", self.pinyin, tone.number()),
+            None => write!(f, "{}", self.pinyin),
+        }
+    }
+}
+
+/// 去除声调，ü 系列的韵母转换成 v
+pub(crate) fn plain_char(ch: char) -> char {
+    match ch {
+        'ü' | 'ǖ' | 'ǘ' | 'ǚ' | 'ǜ' => 'v',
+        _ => TONE_TO_PLAIN.get(&ch).copied().unwrap_or(ch),
+    }
+}
+
+/// 转换成小写并去除声调，如 Nǐ => ni，每个字符对应一个字符
+#[cfg(any(feature = "hmm", feature = "dag"))]
+pub(crate) fn plain_lowercase(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(plain_char)
+        .collect()
+}
+
+/// 借助前缀树切分拼音，同时保留声调
+///
+/// 声调可以是拼音后面的数字 1 到 5，也可以是带声调的韵母，如 lv3you2 => [lv3, you2]；lǚyóu => [lv3, you2]
+pub fn pinyin_tokenize_toned(value: impl AsRef<str>) -> Vec<TonedPinyin> {
+    // 去除声调之后的字符串，以及每个字节位置上的声调
+    let mut plain = String::new();
+    let mut tones = Vec::new();
+    for ch in value.as_ref().chars() {
+        let start = plain.len();
+        plain.push(plain_char(ch));
+        tones.resize(plain.len(), None);
+        tones[start] = Tone::from_diacritic(ch);
+    }
+
+    let mut pinyins = Vec::new();
+    let len = plain.len();
+    let mut i = 0;
+    while i < len {
+        let temp = &plain[i..];
+        let (buf, is_success) = PINYIN_TRIE.find(temp, DEFAULT_SEPARATORS);
+        if is_success && !buf.is_empty() {
+            let end = i + buf.len();
+            let tone = tones[i..end]
+                .iter()
+                .find_map(|tone| *tone)
+                .or_else(|| plain[end..].chars().next().and_then(Tone::from_digit));
+            pinyins.push(TonedPinyin::new(buf, tone));
+            i = end;
+        } else {
+            i += temp.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    pinyins
+}
+
+/// 汉字读音的声调表
+///
+/// 使用和训练模型相同的词典格式，拼音带有数字或者符号声调，没有声调的读音会被忽略：
+///
+/// ```text
+/// 行 xing2 hang2
+/// 银行 yín háng
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ToneTable {
+    chars: HashMap<char, Vec<TonedPinyin>>,
+    phrases: HashMap<String, Vec<TonedPinyin>>,
+}
+
+impl ToneTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        let path = path.as_ref();
+        let file = File::open(path).context(ReadFileSnafu { path })?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, DataError> {
+        let mut table = ToneTable::new();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line.context(ReadLineSnafu { line: idx + 1 })?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(hanzi) = fields.next() else {
+                continue;
+            };
+            let pinyins = fields.map(TonedPinyin::parse).collect::<Vec<_>>();
+            ensure!(
+                !pinyins.is_empty(),
+                InvalidLineSnafu {
+                    line: idx + 1,
+                    reason: "no pinyin"
+                }
+            );
+            let chars = hanzi.chars().collect::<Vec<_>>();
+            if chars.len() == 1 {
+                for pinyin in pinyins {
+                    table.add_reading(chars[0], pinyin);
+                }
+            } else {
+                ensure!(
+                    chars.len() == pinyins.len(),
+                    InvalidLineSnafu {
+                        line: idx + 1,
+                        reason: format!("{} hanzi but {} pinyin", chars.len(), pinyins.len())
+                    }
+                );
+                table.add_phrase(hanzi, pinyins);
+            }
+        }
+        Ok(table)
+    }
+
+    /// 添加单字的一个读音，没有声调的读音会被忽略
+    pub fn add_reading(&mut self, ch: char, pinyin: TonedPinyin) {
+        if pinyin.tone.is_none() {
+            return;
+        }
+        let readings = self.chars.entry(ch).or_default();
+        if !readings.contains(&pinyin) {
+            readings.push(pinyin);
+        }
+    }
+
+    /// 添加词语的读音，词语中每个字的读音也会作为单字的读音
+    pub fn add_phrase(&mut self, phrase: impl Into<String>, pinyins: Vec<TonedPinyin>) {
+        let phrase = phrase.into();
+        for (ch, pinyin) in phrase.chars().zip(&pinyins) {
+            self.add_reading(ch, pinyin.clone());
+        }
+        self.phrases.insert(phrase, pinyins);
+    }
+
+    /// 单字读作 pinyin 时可能的声调，没有记录时返回空
+    pub fn char_tones(&self, ch: char, pinyin: &str) -> Vec<Tone> {
+        self.chars
+            .get(&ch)
+            .into_iter()
+            .flatten()
+            .filter(|reading| reading.pinyin == pinyin)
+            .filter_map(|reading| reading.tone)
+            .collect()
+    }
+
+    /// 单字所有带声调的读音
+    pub fn char_readings(&self, ch: char) -> &[TonedPinyin] {
+        self.chars.get(&ch).map_or(&[], Vec::as_slice)
+    }
+
+    /// 词语带声调的读音
+    pub fn phrase_readings(&self, phrase: &str) -> Option<&[TonedPinyin]> {
+        self.phrases.get(phrase).map(Vec::as_slice)
+    }
+
+    /// 统计汉字和输入的声调不一致的数量，输入没有声调或者声调表中没有记录的字不计入
+    ///
+    /// 词语优先使用词语的读音，否则使用每个字的读音
+    pub fn mismatches(&self, hanzi: &str, observations: &[TonedPinyin]) -> usize {
+        if let Some(readings) = self.phrase_readings(hanzi) {
+            return readings
+                .iter()
+                .zip(observations)
+                .filter(|(reading, observation)| {
+                    observation.tone.is_some()
+                        && reading.tone.is_some()
+                        && reading.tone != observation.tone
+                })
+                .count();
+        }
+        hanzi
+            .chars()
+            .zip(observations)
+            .filter(|(ch, observation)| {
+                let Some(tone) = observation.tone else {
+                    return false;
+                };
+                let tones = self.char_tones(*ch, &observation.pinyin);
+                !tones.is_empty() && !tones.contains(&tone)
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tone, ToneTable, TonedPinyin, pinyin_tokenize_toned};
+
+    #[test]
+    fn test_pinyin_tokenize_toned() {
+        let pinyins = pinyin_tokenize_toned("lv3you2");
+        assert_eq!(
+            pinyins,
+            [
+                TonedPinyin::new("lv", Some(Tone::Third)),
+                TonedPinyin::new("you", Some(Tone::Second))
+            ]
+        );
+        assert_eq!(pinyin_tokenize_toned("lǚyóu"), pinyins);
+        let pinyins = pinyin_tokenize_toned("ni3hao");
+        assert_eq!(pinyins[0].to_string(), "ni3");
+        assert_eq!(pinyins[1].to_string(), "hao");
+        let pinyins = pinyin_tokenize_toned("xi'an1");
+        assert_eq!(pinyins[0].tone, None);
+        assert_eq!(pinyins[1].tone, Some(Tone::First));
+
+        assert_eq!(
+            TonedPinyin::parse("Xíng"),
+            TonedPinyin::new("xing", Some(Tone::Second))
+        );
+        assert_eq!(
+            TonedPinyin::parse("lu:4"),
+            TonedPinyin::new("lv", Some(Tone::Fourth))
+        );
+    }
+
+    #[test]
+    fn test_tone_table() {
+        let lexicon = "# 注释\n行 xing2 hang2\n银行 yín háng\n好 hao\n";
+        let table = ToneTable::from_reader(lexicon.as_bytes()).unwrap();
+        assert_eq!(table.char_tones('行', "xing"), [Tone::Second]);
+        assert_eq!(table.char_tones('银', "yin"), [Tone::Second]);
+        // 没有声调的读音会被忽略
+        assert!(table.char_tones('好', "hao").is_empty());
+
+        let observations = [
+            TonedPinyin::new("yin", Some(Tone::Second)),
+            TonedPinyin::new("hang", Some(Tone::Fourth)),
+        ];
+        assert_eq!(table.mismatches("银行", &observations), 1);
+        let observations = [TonedPinyin::new("hao", Some(Tone::Third))];
+        assert_eq!(table.mismatches("好", &observations), 0);
+
+        assert!(ToneTable::from_reader("银行 yin".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_pinyin_style() {
+        use super::PinyinStyle;
+
+        let zhong = TonedPinyin::new("zhong", Some(Tone::First));
+        assert_eq!(zhong.format(PinyinStyle::Plain), "zhong");
+        assert_eq!(zhong.format(PinyinStyle::ToneMarks), "zhōng");
+        assert_eq!(zhong.format(PinyinStyle::ToneNumbers), "zhong1");
+        assert_eq!(zhong.format(PinyinStyle::Initials), "zh");
+        let marked = ["lv3", "gui4", "liu2", "dou1", "xue2", "an4", "de5", "hao"]
+            .map(|pinyin| TonedPinyin::parse(pinyin).format(PinyinStyle::ToneMarks));
+        assert_eq!(
+            marked,
+            ["lǚ", "guì", "liú", "dōu", "xué", "àn", "de", "hao"]
+        );
+        assert_eq!(
+            TonedPinyin::new("an", None).format(PinyinStyle::Initials),
+            "a"
+        );
+    }
+}
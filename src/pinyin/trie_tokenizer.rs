@@ -427,7 +427,7 @@ pub(crate) static PINYIN_TRIE: LazyLock<TrieNode> = LazyLock::new(|| {
     trie
 });
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(unused)]
 pub(crate) struct TrieNode<'a> {
     key: &'a str,
@@ -451,7 +451,19 @@ impl<'a> TrieNode<'a> {
         node
     }
 
-    fn add(&mut self, seq: &[&'a str]) {
+    /// 所有的 (声母, 韵母)，零声母的拼音声母为空
+    pub(crate) fn syllables(&self) -> Vec<(&'a str, &'a str)> {
+        let mut syllables = Vec::new();
+        for (key, child) in self.children.iter() {
+            if child.is_end {
+                syllables.push(("", *key));
+            }
+            syllables.extend(child.children.keys().map(|rest| (*key, *rest)));
+        }
+        syllables
+    }
+
+    pub(crate) fn add(&mut self, seq: &[&'a str]) {
         if seq.is_empty() {
             self.is_end = true;
         } else {
@@ -466,15 +478,21 @@ impl<'a> TrieNode<'a> {
         }
     }
 
-    pub(crate) fn find(&self, sentence: impl AsRef<str>) -> (String, bool) {
+    /// 从 sentence 的开头找到一个拼音，遇到分隔符时停止，拼音不会跨过分隔符
+    pub(crate) fn find(&self, sentence: impl AsRef<str>, separators: &[char]) -> (String, bool) {
         let sentence = sentence.as_ref();
+        let end = sentence.find(separators).unwrap_or(sentence.len());
+        self.find_prefix(&sentence[..end])
+    }
+
+    fn find_prefix(&self, sentence: &str) -> (String, bool) {
         let len = sentence.len();
         for i in 0..len {
             let j = len - i;
-            if j <= len {
+            if sentence.is_char_boundary(j) {
                 let key = &sentence[0..j];
                 if let Some(child) = self.children.get(key) {
-                    let (buf, is_success) = child.find(&sentence[j..]);
+                    let (buf, is_success) = child.find_prefix(&sentence[j..]);
                     if is_success {
                         // 目前特殊字符只有 g
                         if !buf.is_empty()
@@ -483,13 +501,13 @@ impl<'a> TrieNode<'a> {
                         {
                             // 看一下 buf 去掉特殊字符之后，是不是拼音
                             let (sub_buf, find_buf_child_is_success) =
-                                child.find(&buf[0..buf.len() - 1]);
+                                child.find_prefix(&buf[0..buf.len() - 1]);
                             // 看一下特殊字符以及后面的字符能不能组成一个拼音
                             let special_key = &buf[buf.len() - 1..];
                             let (_, retry_find_child_is_success) =
                                 if let Some(special_node) = self.children.get(special_key) {
                                     let new_sentence = &sentence[j + buf.len()..];
-                                    special_node.find(new_sentence)
+                                    special_node.find_prefix(new_sentence)
                                 } else {
                                     ("".to_owned(), false)
                                 };
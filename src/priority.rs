@@ -1,17 +1,34 @@
 use std::clone::Clone;
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashSet};
+use std::ops::Range;
+
+/// 路径中每个元素对应的拼音范围（拼音序列的下标）和局部概率
+pub type Span = (Range<usize>, f64);
 
 /// 项目包含分数和路径
 #[derive(Debug, Clone)]
 pub struct Item {
     score: f64,
     path: Vec<String>,
+    spans: Vec<Span>,
+    pinyins: Vec<String>,
 }
 
 impl Item {
-    pub fn new(score: f64, path: Vec<String>) -> Self {
-        Item { score, path }
+    pub fn new(score: f64, path: Vec<String>, spans: Vec<Span>) -> Self {
+        Item {
+            score,
+            path,
+            spans,
+            pinyins: Vec::new(),
+        }
+    }
+
+    /// 记录路径经过的拼音，在拼音网格或者简拼上解码时，每条路径的拼音可能不同
+    pub fn with_pinyins(mut self, pinyins: Vec<String>) -> Self {
+        self.pinyins = pinyins;
+        self
     }
 
     pub fn score(&self) -> f64 {
@@ -21,6 +38,16 @@ impl Item {
     pub fn path(&self) -> &Vec<String> {
         &self.path
     }
+
+    /// 和 path 一一对应的拼音范围和局部概率
+    pub fn spans(&self) -> &Vec<Span> {
+        &self.spans
+    }
+
+    /// 路径经过的拼音，只有在拼音网格或者简拼上解码时才会记录
+    pub fn pinyins(&self) -> &Vec<String> {
+        &self.pinyins
+    }
 }
 
 impl PartialEq for Item {
@@ -67,8 +94,7 @@ impl PrioritySet {
         }
     }
 
-    pub fn put(&mut self, score: f64, path: Vec<String>) {
-        let item = Item::new(score, path);
+    pub fn put(&mut self, item: Item) {
         self.data.push(item);
 
         // 保持容量限制
@@ -78,15 +104,34 @@ impl PrioritySet {
         }
     }
 
+    /// 和 put 相同，但是汉字相同的路径只保留分数最高的一条，集合中路径的汉字互不相同
+    #[cfg(feature = "dag")]
+    pub fn put_unique(&mut self, item: Item) {
+        let text = item.path().concat();
+        if let Some(existing) = self.data.iter().find(|other| other.path().concat() == text) {
+            if existing.score >= item.score {
+                return;
+            }
+            self.data.retain(|other| other.path().concat() != text);
+        }
+        self.put(item);
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    #[cfg(feature = "dag")]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Item> {
         self.data.iter()
     }
 
     /// 转换为排序的向量（按分数降序）
+    #[cfg(feature = "dag")]
     pub fn to_sorted_vec(&self) -> Vec<Item> {
         let mut items: Vec<Item> = self.data.iter().cloned().collect();
         items.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
@@ -94,6 +139,15 @@ impl PrioritySet {
     }
 }
 
+/// 按分数降序排列的结果中，相同的汉字只保留第一个
+pub(crate) fn dedup_by_text(items: Vec<Item>) -> Vec<Item> {
+    let mut seen = HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| seen.insert(item.path().concat()))
+        .collect()
+}
+
 impl std::fmt::Display for PrioritySet {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "[")?;
@@ -111,11 +165,11 @@ mod tests {
     #[test]
     fn test_priority_set() {
         let mut ps = PrioritySet::new(3);
-        ps.put(0.5, vec!["你".to_string()]);
-        ps.put(0.8, vec!["我".to_string()]);
-        ps.put(0.3, vec!["他".to_string()]);
+        ps.put(Item::new(0.5, vec!["你".to_string()], vec![(0..1, 0.5)]));
+        ps.put(Item::new(0.8, vec!["我".to_string()], vec![(0..1, 0.8)]));
+        ps.put(Item::new(0.3, vec!["他".to_string()], vec![(0..1, 0.3)]));
         // 应该替换掉0.3的元素
-        ps.put(0.9, vec!["她".to_string()]);
+        ps.put(Item::new(0.9, vec!["她".to_string()], vec![(0..1, 0.9)]));
 
         assert_eq!(ps.len(), 3);
 
@@ -0,0 +1,109 @@
+use snafu::{Whatever, prelude::*};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// 汉字到拼音的词典
+///
+/// 每行一个条目，汉字和拼音之间用空白字符分隔，`#` 开头的行是注释：
+///
+/// ```text
+/// 行 xing hang heng
+/// 银行 yin hang
+/// ```
+///
+/// 单字条目后面可以跟多个读音；词语条目的拼音数量必须和字数一致，每个字对应一个拼音
+#[derive(Debug, Default)]
+pub struct Lexicon {
+    /// 单字的所有读音，按出现的顺序排列
+    pub chars: BTreeMap<char, Vec<String>>,
+    /// 词语和它的拼音
+    pub phrases: Vec<(String, Vec<String>)>,
+}
+
+impl Lexicon {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Whatever> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_whatever_context(|_| format!("Couldn't open {}", path.display()))?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, Whatever> {
+        let mut lexicon = Lexicon::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line.with_whatever_context(|_| "Couldn't read line from lexicon")?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(hanzi) = fields.next() else {
+                continue;
+            };
+            let pinyins = fields.map(normalize_pinyin).collect::<Vec<_>>();
+            if pinyins.is_empty() {
+                whatever!("Line {} of lexicon has no pinyin", idx + 1);
+            }
+            let chars = hanzi.chars().collect::<Vec<_>>();
+            if chars.len() == 1 {
+                for pinyin in pinyins {
+                    lexicon.add_reading(chars[0], pinyin);
+                }
+            } else {
+                if chars.len() != pinyins.len() {
+                    whatever!(
+                        "Line {} of lexicon has {} hanzi but {} pinyin",
+                        idx + 1,
+                        chars.len(),
+                        pinyins.len()
+                    );
+                }
+                for (ch, pinyin) in chars.iter().zip(&pinyins) {
+                    lexicon.add_reading(*ch, pinyin.clone());
+                }
+                lexicon.phrases.push((hanzi.to_owned(), pinyins));
+            }
+        }
+        Ok(lexicon)
+    }
+
+    /// 获取单字的所有读音
+    pub fn readings(&self, ch: char) -> Option<&Vec<String>> {
+        self.chars.get(&ch)
+    }
+
+    fn add_reading(&mut self, ch: char, pinyin: String) {
+        let readings = self.chars.entry(ch).or_default();
+        if !readings.contains(&pinyin) {
+            readings.push(pinyin);
+        }
+    }
+}
+
+/// 统一拼音的写法：小写，去掉数字声调，ü 和 u: 写成 v
+pub fn normalize_pinyin(pinyin: &str) -> String {
+    pinyin
+        .to_lowercase()
+        .replace("u:", "v")
+        .replace('ü', "v")
+        .trim_end_matches(|ch: char| ch.is_ascii_digit())
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lexicon;
+
+    #[test]
+    fn test_lexicon_from_reader() {
+        let text = "# 注释\n行 xing2 hang2\n银行 yin hang\n绿 lü4\n";
+        let lexicon = Lexicon::from_reader(text.as_bytes()).unwrap();
+        assert_eq!(lexicon.readings('行').unwrap(), &["xing", "hang"]);
+        assert_eq!(lexicon.readings('银').unwrap(), &["yin"]);
+        assert_eq!(lexicon.readings('绿').unwrap(), &["lv"]);
+        assert_eq!(lexicon.phrases.len(), 1);
+        assert!(Lexicon::from_reader("银行 yin".as_bytes()).is_err());
+    }
+}
@@ -1,7 +1,15 @@
 use crate::task::convert_to_rkyv;
+use crate::train_dag::train_dag;
+use crate::train_hmm::train_hmm;
+use crate::train_ngram::train_ngram;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
+mod lexicon;
 mod task;
+mod train_dag;
+mod train_hmm;
+mod train_ngram;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -14,12 +22,63 @@ struct Cli {
 enum Action {
     #[command(about = "Convert the json file to a binary file in rkyv format.")]
     ConvertToRkyv,
+    #[command(about = "Train the HMM model from a hanzi corpus and a hanzi-pinyin lexicon.")]
+    TrainHmm {
+        /// 语料文件，每行一段汉字文本
+        #[arg(long)]
+        corpus: PathBuf,
+        /// 词典文件，每行是汉字和对应的拼音
+        #[arg(long)]
+        lexicon: PathBuf,
+        /// 输出 json 文件的目录
+        #[arg(long, default_value = "data")]
+        output: PathBuf,
+    },
+    #[command(about = "Train the DAG model from a word frequency list and a hanzi-pinyin lexicon.")]
+    TrainDag {
+        /// 词频文件，每行是词语和频次
+        #[arg(long)]
+        words: PathBuf,
+        /// 词典文件，每行是汉字和对应的拼音
+        #[arg(long)]
+        lexicon: PathBuf,
+        /// 输出 json 文件的目录
+        #[arg(long, default_value = "data")]
+        output: PathBuf,
+    },
+    #[command(about = "Train the word n-gram language model from a segmented corpus.")]
+    TrainNgram {
+        /// 语料文件，每行一个句子，词语之间用空白字符分隔
+        #[arg(long)]
+        corpus: PathBuf,
+        /// 模型的阶数
+        #[arg(long, default_value_t = 3)]
+        order: usize,
+        /// 输出 json 文件的目录
+        #[arg(long, default_value = "data")]
+        output: PathBuf,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
     match cli.action {
         Action::ConvertToRkyv => convert_to_rkyv(),
+        Action::TrainHmm {
+            corpus,
+            lexicon,
+            output,
+        } => train_hmm(corpus, lexicon, output),
+        Action::TrainDag {
+            words,
+            lexicon,
+            output,
+        } => train_dag(words, lexicon, output),
+        Action::TrainNgram {
+            corpus,
+            order,
+            output,
+        } => train_ngram(corpus, order, output),
     }
     .unwrap();
 }
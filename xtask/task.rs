@@ -1,5 +1,6 @@
 use pinyinchch_type::dag::{DagChar, DagPhrase};
 use pinyinchch_type::hmm::{HmmData, HmmEmission, HmmPy2Hz, HmmTransition};
+use pinyinchch_type::ngram::NgramTable;
 use rkyv::util::AlignedVec;
 use snafu::{Whatever, prelude::*};
 use std::fs::{File, create_dir_all, read_dir};
@@ -24,6 +25,26 @@ pub fn convert_to_rkyv() -> Result<(), Whatever> {
     Ok(())
 }
 
+/// 将数据以 json 格式写入文件，如果目录不存在则创建
+pub fn write_json(path: impl AsRef<Path>, value: &impl serde::Serialize) -> Result<(), Whatever> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        create_dir_all(parent)
+            .with_whatever_context(|_| format!("Couldn't create dir {}", parent.display()))?;
+    }
+    let file = File::create(path)
+        .with_whatever_context(|_| format!("Couldn't create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, value)
+        .with_whatever_context(|_| format!("Couldn't write to {}", path.display()))?;
+    writer
+        .flush()
+        .with_whatever_context(|_| format!("Couldn't flush {}", path.display()))?;
+    Ok(())
+}
+
 fn to_rkyv(path: impl AsRef<Path>) -> Result<(), Whatever> {
     let path = path.as_ref();
     let Some(file_name) = path.file_name().and_then(std::ffi::OsStr::to_str) else {
@@ -121,6 +142,15 @@ fn to_bytes(
             let (rkyv_file_name, path) = get_rkyv_name_and_path(file_name, "pinyinchch-model-hmm")?;
             (bytes, rkyv_file_name, path)
         }
+        "ngram.json" => {
+            let ngram = serde_json::from_reader::<_, NgramTable>(reader)
+                .with_whatever_context(|_| "Couldn't read ngram.json")?;
+            let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&ngram)
+                .with_whatever_context(|_| "The ngram couldn't serialize to rkyv")?;
+            let (rkyv_file_name, path) =
+                get_rkyv_name_and_path(file_name, "pinyinchch-model-ngram")?;
+            (bytes, rkyv_file_name, path)
+        }
         _ => whatever!("This type of file conversion is not supported"),
     };
 
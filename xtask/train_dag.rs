@@ -0,0 +1,175 @@
+// 训练流程来自 https://github.com/letiantian/Pinyin2Hanzi/tree/master/train/dag
+
+use crate::lexicon::Lexicon;
+use crate::task::write_json;
+use pinyinchch_type::dag::{DagChar, DagPhrase};
+use snafu::{Whatever, prelude::*};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// DAG 模型的训练结果
+pub struct DagTables {
+    pub char: DagChar,
+    pub phrase: DagPhrase,
+}
+
+/// 从词频表和词典训练 DAG 模型，在 output 目录中写入 dag_char.json 和 dag_phrase.json
+pub fn train_dag(
+    words: impl AsRef<Path>,
+    lexicon: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<(), Whatever> {
+    let words = words.as_ref();
+    let output = output.as_ref();
+    let lexicon = Lexicon::from_path(lexicon)?;
+    let file = File::open(words)
+        .with_whatever_context(|_| format!("Couldn't open {}", words.display()))?;
+    let tables = train(BufReader::new(file), &lexicon)?;
+    write_json(output.join("dag_char.json"), &tables.char)?;
+    write_json(output.join("dag_phrase.json"), &tables.phrase)?;
+    Ok(())
+}
+
+/// 词频表中每行是一个词语和它的频次，用空白字符分隔，省略频次时记为 1
+///
+/// 词语的拼音优先使用词典中的词语条目，否则使用每个字在词典中的第一个读音；
+/// 词典中没有收录的字组成的词语会被忽略。词典中的单字至少记一次，保证每个字都能被查到
+pub fn train(words: impl BufRead, lexicon: &Lexicon) -> Result<DagTables, Whatever> {
+    let mut phrase_readings = HashMap::<&str, Vec<&Vec<String>>>::new();
+    for (phrase, pinyins) in &lexicon.phrases {
+        phrase_readings.entry(phrase).or_default().push(pinyins);
+    }
+
+    let mut char_count = HashMap::<String, HashMap<String, f64>>::new();
+    let mut phrase_count = HashMap::<String, HashMap<String, f64>>::new();
+    for (ch, pinyins) in &lexicon.chars {
+        for pinyin in pinyins {
+            char_count
+                .entry(pinyin.clone())
+                .or_default()
+                .insert(ch.to_string(), 1.0);
+        }
+    }
+
+    for (idx, line) in words.lines().enumerate() {
+        let line = line.with_whatever_context(|_| "Couldn't read line from word list")?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let Some(word) = fields.next() else {
+            continue;
+        };
+        let count = match fields.next() {
+            Some(count) => count
+                .parse::<f64>()
+                .with_whatever_context(|_| format!("Line {} has an invalid count", idx + 1))?,
+            None => 1.0,
+        };
+
+        let chars = word.chars().collect::<Vec<_>>();
+        if chars.len() == 1 {
+            let Some(pinyins) = lexicon.readings(chars[0]) else {
+                continue;
+            };
+            for pinyin in pinyins {
+                *char_count
+                    .entry(pinyin.clone())
+                    .or_default()
+                    .entry(word.to_owned())
+                    .or_default() += count;
+            }
+            continue;
+        }
+
+        let keys = if let Some(readings) = phrase_readings.get(word) {
+            readings.iter().map(|pinyins| pinyins.join(",")).collect()
+        } else if let Some(pinyins) = chars
+            .iter()
+            .map(|ch| lexicon.readings(*ch).and_then(|pinyins| pinyins.first()))
+            .collect::<Option<Vec<_>>>()
+        {
+            vec![
+                pinyins
+                    .iter()
+                    .map(|pinyin| pinyin.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ]
+        } else {
+            continue;
+        };
+        for key in keys {
+            *phrase_count
+                .entry(key)
+                .or_default()
+                .entry(word.to_owned())
+                .or_default() += count;
+        }
+    }
+
+    Ok(DagTables {
+        char: DagChar {
+            data: normalize(char_count),
+        },
+        phrase: DagPhrase {
+            data: normalize(phrase_count),
+        },
+    })
+}
+
+/// 按拼音归一化概率，并且按概率降序排列，`DefaultDag::get_phrase` 依赖这个顺序截取结果
+fn normalize(count: HashMap<String, HashMap<String, f64>>) -> HashMap<String, Vec<(String, f64)>> {
+    count
+        .into_iter()
+        .map(|(key, hanzi_count)| {
+            let sum = hanzi_count.values().sum::<f64>();
+            let mut hanzi_prob = hanzi_count
+                .into_iter()
+                .map(|(hanzi, count)| (hanzi, count / sum))
+                .collect::<Vec<_>>();
+            hanzi_prob.sort_by(|a, b| {
+                b.1.partial_cmp(&a.1)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| a.0.cmp(&b.0))
+            });
+            (key, hanzi_prob)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::train;
+    use crate::lexicon::Lexicon;
+
+    #[test]
+    fn test_train_dag() {
+        let lexicon = "你 ni\n泥 ni\n好 hao\n行 xing hang\n银 yin\n银行 yin hang\n";
+        let lexicon = Lexicon::from_reader(lexicon.as_bytes()).unwrap();
+        let words = "你 7\n你好 10\n银行 3\n行 2\n好行\n未知 5\n";
+        let tables = train(words.as_bytes(), &lexicon).unwrap();
+
+        let ni = &tables.char.data["ni"];
+        // 词典中的单字先记一次
+        assert_eq!(ni[0], ("你".to_owned(), 8.0 / 9.0));
+        assert_eq!(ni[1], ("泥".to_owned(), 1.0 / 9.0));
+        assert_eq!(tables.char.data["hang"][0].0, "行");
+        assert_eq!(tables.phrase.data["ni,hao"], [("你好".to_owned(), 1.0)]);
+        assert_eq!(tables.phrase.data["yin,hang"], [("银行".to_owned(), 1.0)]);
+        // 没有词语条目时使用每个字的第一个读音
+        assert_eq!(tables.phrase.data["hao,xing"], [("好行".to_owned(), 1.0)]);
+        assert!(
+            !tables
+                .phrase
+                .data
+                .values()
+                .flatten()
+                .any(|(hz, _)| hz == "未知")
+        );
+    }
+}
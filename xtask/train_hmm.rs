@@ -0,0 +1,168 @@
+// 训练流程来自 https://github.com/letiantian/Pinyin2Hanzi/tree/master/train/hmm
+
+use crate::lexicon::Lexicon;
+use crate::task::write_json;
+use pinyinchch_type::hmm::{HmmData, HmmEmission, HmmPy2Hz, HmmTransition};
+use snafu::{Whatever, prelude::*};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// 概率为 0 时使用的默认值
+const MIN_PROB: f64 = 1.0e-200;
+
+/// 每一行的转移概率中，未见过的汉字使用的 key
+const DEFAULT: &str = "default";
+
+/// HMM 模型的训练结果
+pub struct HmmTables {
+    pub start: HmmData,
+    pub emission: HmmEmission,
+    pub transition: HmmTransition,
+    pub py2hz: HmmPy2Hz,
+}
+
+/// 从语料和词典训练 HMM 模型，在 output 目录中写入 hmm_*.json
+pub fn train_hmm(
+    corpus: impl AsRef<Path>,
+    lexicon: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<(), Whatever> {
+    let corpus = corpus.as_ref();
+    let output = output.as_ref();
+    let lexicon = Lexicon::from_path(lexicon)?;
+    let file = File::open(corpus)
+        .with_whatever_context(|_| format!("Couldn't open {}", corpus.display()))?;
+    let tables = train(BufReader::new(file), &lexicon)?;
+    write_json(output.join("hmm_start.json"), &tables.start)?;
+    write_json(output.join("hmm_emission.json"), &tables.emission)?;
+    write_json(output.join("hmm_transition.json"), &tables.transition)?;
+    write_json(output.join("hmm_py2hz.json"), &tables.py2hz)?;
+    Ok(())
+}
+
+/// 语料中每行是一段汉字文本，不在词典中的字符（标点、数字等）会把文本切成多个句子
+pub fn train(corpus: impl BufRead, lexicon: &Lexicon) -> Result<HmmTables, Whatever> {
+    let mut start_count = HashMap::<String, f64>::new();
+    let mut transition_count = HashMap::<String, HashMap<String, f64>>::new();
+    for line in corpus.lines() {
+        let line = line.with_whatever_context(|_| "Couldn't read line from corpus")?;
+        for sentence in line.split(|ch: char| lexicon.readings(ch).is_none()) {
+            let mut chars = sentence.chars();
+            let Some(first) = chars.next() else {
+                continue;
+            };
+            *start_count.entry(first.to_string()).or_default() += 1.0;
+            let mut prev = first;
+            for ch in chars {
+                *transition_count
+                    .entry(prev.to_string())
+                    .or_default()
+                    .entry(ch.to_string())
+                    .or_default() += 1.0;
+                prev = ch;
+            }
+        }
+    }
+    if start_count.is_empty() {
+        whatever!("The corpus doesn't contain any hanzi in the lexicon");
+    }
+
+    Ok(HmmTables {
+        start: gen_start(start_count),
+        emission: gen_emission(lexicon),
+        transition: gen_transition(transition_count),
+        py2hz: gen_py2hz(lexicon),
+    })
+}
+
+fn gen_start(mut start_count: HashMap<String, f64>) -> HmmData {
+    let sum = start_count.values().sum::<f64>();
+    for count in start_count.values_mut() {
+        *count /= sum;
+    }
+    HmmData {
+        data: start_count,
+        default: 1.0 / sum,
+    }
+}
+
+/// 单字的每个读音记一次，词语中每个字的读音再各记一次
+fn gen_emission(lexicon: &Lexicon) -> HmmEmission {
+    let mut emission = HashMap::<String, HashMap<String, f64>>::new();
+    let phrase_pairs = lexicon
+        .phrases
+        .iter()
+        .flat_map(|(phrase, pinyins)| phrase.chars().zip(pinyins.iter().map(String::as_str)));
+    let char_pairs = lexicon
+        .chars
+        .iter()
+        .flat_map(|(ch, pinyins)| pinyins.iter().map(move |pinyin| (*ch, pinyin.as_str())));
+    for (ch, pinyin) in char_pairs.chain(phrase_pairs) {
+        *emission
+            .entry(ch.to_string())
+            .or_default()
+            .entry(pinyin.to_owned())
+            .or_default() += 1.0;
+    }
+    for prob_dict in emission.values_mut() {
+        let sum = prob_dict.values().sum::<f64>();
+        for prob in prob_dict.values_mut() {
+            *prob /= sum;
+        }
+    }
+    HmmEmission {
+        data: emission,
+        default: MIN_PROB,
+    }
+}
+
+fn gen_transition(mut transition_count: HashMap<String, HashMap<String, f64>>) -> HmmTransition {
+    for prob_dict in transition_count.values_mut() {
+        let sum = prob_dict.values().sum::<f64>();
+        for prob in prob_dict.values_mut() {
+            *prob /= sum;
+        }
+        prob_dict.insert(DEFAULT.to_owned(), 1.0 / sum);
+    }
+    HmmTransition {
+        data: transition_count,
+        default: MIN_PROB,
+    }
+}
+
+fn gen_py2hz(lexicon: &Lexicon) -> HmmPy2Hz {
+    let mut py2hz = HashMap::<String, String>::new();
+    for (ch, pinyins) in &lexicon.chars {
+        for pinyin in pinyins {
+            py2hz.entry(pinyin.clone()).or_default().push(*ch);
+        }
+    }
+    HmmPy2Hz { data: py2hz }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT, train};
+    use crate::lexicon::Lexicon;
+
+    #[test]
+    fn test_train_hmm() {
+        let lexicon = "你 ni\n好 hao\n我 wo\n行 xing hang\n银行 yin hang\n银 yin\n";
+        let lexicon = Lexicon::from_reader(lexicon.as_bytes()).unwrap();
+        let corpus = "你好，我好\n银行行\n";
+        let tables = train(corpus.as_bytes(), &lexicon).unwrap();
+
+        assert_eq!(tables.start.data["你"], 1.0 / 3.0);
+        assert_eq!(tables.start.default, 1.0 / 3.0);
+        assert_eq!(tables.transition.data["你"]["好"], 1.0);
+        assert_eq!(tables.transition.data["行"]["行"], 1.0);
+        assert_eq!(tables.transition.data["行"][DEFAULT], 1.0);
+        // 单字的两个读音各一次，词语中的读音一次
+        assert_eq!(tables.emission.data["行"]["hang"], 2.0 / 3.0);
+        assert_eq!(tables.py2hz.data["hang"], "行");
+        assert_eq!(tables.py2hz.data["yin"], "银");
+        assert!(train("abc".as_bytes(), &lexicon).is_err());
+    }
+}
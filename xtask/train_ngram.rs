@@ -0,0 +1,178 @@
+use crate::task::write_json;
+use pinyinchch_type::ngram::{NgramTable, SENTENCE_END, SENTENCE_START, backoff_log_prob};
+use snafu::{Whatever, prelude::*};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// 没有接续概率的 n 元组（如 `<s>`）使用的对数概率，和 ARPA 格式一致
+const IMPOSSIBLE_LOG_PROB: f64 = -99.0;
+
+/// 从分好词的语料训练 n 元语法模型，在 output 目录中写入 ngram.json
+pub fn train_ngram(
+    corpus: impl AsRef<Path>,
+    order: usize,
+    output: impl AsRef<Path>,
+) -> Result<(), Whatever> {
+    let corpus = corpus.as_ref();
+    let file = File::open(corpus)
+        .with_whatever_context(|_| format!("Couldn't open {}", corpus.display()))?;
+    let table = train(BufReader::new(file), order)?;
+    write_json(output.as_ref().join("ngram.json"), &table)?;
+    Ok(())
+}
+
+/// 语料中每行是一个句子，词语之间用空白字符分隔
+///
+/// 使用插值的 Kneser-Ney 平滑，每一阶的折扣是 n1 / (n1 + 2 * n2)，
+/// 结果保存成回退形式：出现过的 n 元组记录插值之后的概率，上文记录剩余的概率作为回退权重
+pub fn train(corpus: impl BufRead, order: usize) -> Result<NgramTable, Whatever> {
+    ensure_whatever!(order >= 1, "The order must be at least 1");
+
+    // counts[k - 1] 是 k 元组的次数
+    let mut counts = vec![HashMap::<Vec<String>, f64>::new(); order];
+    for line in corpus.lines() {
+        let line = line.with_whatever_context(|_| "Couldn't read line from corpus")?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() {
+            continue;
+        }
+        let tokens = std::iter::once(SENTENCE_START)
+            .chain(words)
+            .chain(std::iter::once(SENTENCE_END))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        for k in 1..=order {
+            for gram in tokens.windows(k) {
+                if k == 1 && gram[0] == SENTENCE_START {
+                    continue;
+                }
+                *counts[k - 1].entry(gram.to_vec()).or_default() += 1.0;
+            }
+        }
+    }
+    let vocab_size = counts[0].len() as f64;
+    ensure_whatever!(vocab_size > 0.0, "The corpus is empty");
+
+    // 除了最高阶，使用接续次数，即前面可以接多少种不同的词；以 <s> 开头的 n 元组前面没有词，使用原始次数
+    let mut adjusted = counts.clone();
+    for k in 1..order {
+        let mut continuation = HashMap::<&[String], f64>::new();
+        for gram in counts[k].keys() {
+            *continuation.entry(&gram[1..]).or_default() += 1.0;
+        }
+        for (gram, count) in adjusted[k - 1].iter_mut() {
+            if gram[0] != SENTENCE_START
+                && let Some(cont) = continuation.get(gram.as_slice())
+            {
+                *count = *cont;
+            }
+        }
+    }
+
+    let mut table = NgramTable {
+        order: order as u32,
+        data: HashMap::new(),
+        unknown: 0.0,
+    };
+    for (k, grams) in adjusted
+        .iter()
+        .enumerate()
+        .map(|(idx, grams)| (idx + 1, grams))
+    {
+        let discount = discount(grams);
+        // 每个上文下的次数之和，以及出现过的词的数量
+        let mut contexts = HashMap::<&[String], (f64, f64)>::new();
+        for (gram, count) in grams {
+            let (sum, types) = contexts.entry(&gram[..k - 1]).or_default();
+            *sum += count;
+            *types += 1.0;
+        }
+
+        let mut probs = Vec::with_capacity(grams.len());
+        for (gram, count) in grams {
+            let (sum, types) = contexts[&gram[..k - 1]];
+            let lower = if k == 1 {
+                1.0 / vocab_size
+            } else {
+                let history = gram[1..k - 1]
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                backoff_log_prob(k - 1, &history, &gram[k - 1], table.unknown, |key| {
+                    table.data.get(key).copied()
+                })
+                .exp()
+            };
+            let prob = f64::max(count - discount, 0.0) / sum + discount * types / sum * lower;
+            probs.push((gram.join(" "), prob.ln()));
+        }
+        for (key, log_prob) in probs {
+            table.data.entry(key).or_insert((0.0, 0.0)).0 = log_prob;
+        }
+
+        for (history, (sum, types)) in contexts {
+            let bow = (discount * types / sum).ln();
+            if history.is_empty() {
+                // 剩余的概率平均分给所有的词，作为未登录词的概率
+                table.unknown = bow - vocab_size.ln();
+            } else {
+                table
+                    .data
+                    .entry(history.join(" "))
+                    .or_insert((IMPOSSIBLE_LOG_PROB, 0.0))
+                    .1 = bow;
+            }
+        }
+    }
+    Ok(table)
+}
+
+/// 根据出现一次和两次的 n 元组的数量估计折扣
+fn discount(grams: &HashMap<Vec<String>, f64>) -> f64 {
+    let n1 = grams.values().filter(|count| **count == 1.0).count() as f64;
+    let n2 = grams.values().filter(|count| **count == 2.0).count() as f64;
+    if n1 > 0.0 && n2 > 0.0 {
+        n1 / (n1 + 2.0 * n2)
+    } else {
+        0.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::train;
+    use pinyinchch_type::ngram::{NgramTable, backoff_log_prob};
+
+    fn log_prob(table: &NgramTable, context: &[&str], word: &str) -> f64 {
+        backoff_log_prob(table.order as usize, context, word, table.unknown, |key| {
+            table.data.get(key).copied()
+        })
+    }
+
+    #[test]
+    fn test_train_ngram() {
+        let corpus = "你 好\n你 好 吗\n我 很 好\n\n我 好\n";
+        let table = train(corpus.as_bytes(), 3).unwrap();
+        assert_eq!(table.order, 3);
+        let vocab = ["你", "好", "吗", "我", "很", "</s>"];
+        // 每个上文下所有词的概率之和为 1
+        for context in [
+            &[][..],
+            &["你"],
+            &["<s>", "我"],
+            &["你", "好"],
+            &["很", "你"],
+        ] {
+            let sum = vocab
+                .iter()
+                .map(|word| log_prob(&table, context, word).exp())
+                .sum::<f64>();
+            assert!((sum - 1.0).abs() < 1e-9, "{context:?} {sum}");
+        }
+        assert!(log_prob(&table, &["你"], "好") > log_prob(&table, &["你"], "吗"));
+        assert!(log_prob(&table, &["我"], "未知") < log_prob(&table, &["我"], "吗"));
+        assert!(train("".as_bytes(), 2).is_err());
+    }
+}